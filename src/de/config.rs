@@ -0,0 +1,98 @@
+/// Default maximum nesting depth of constructed values.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Limits the deserializer enforces on hostile input.
+///
+/// Apart from the nesting depth nothing is limited by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderConfig {
+    /// Maximum number of bytes of the input.
+    pub max_input_len: usize,
+    /// Maximum content length of a single value.
+    pub max_element_len: usize,
+    /// Maximum nesting depth of constructed values.
+    pub max_depth: usize,
+    /// Maximum number of components of a constructed value, like elements of SEQUENCE OF.
+    pub max_seq_len: usize,
+    /// Maximum number of OBJECT IDENTIFIER arcs.
+    pub max_oid_arcs: usize,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        DecoderConfig {
+            max_input_len: usize::max_value(),
+            max_element_len: usize::max_value(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_seq_len: usize::max_value(),
+            max_oid_arcs: usize::max_value(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use super::DecoderConfig;
+    use de::{Asn1Deserialize, Asn1DeserializeOwned};
+    use der::{DecodeError, Deserializer};
+    use universal::{ObjectIdentifier, OctetString, Value};
+
+    fn check_limit<T>(value: &[u8], config: DecoderConfig) -> DecodeError
+        where T: Asn1DeserializeOwned + Debug
+    {
+        let de = Deserializer::new(value).with_config(config);
+        T::asn1_deserialize(de).unwrap_err()
+    }
+
+    #[test]
+    fn limits() {
+        let default = DecoderConfig::default();
+
+        let buf = ::to_asn1(&OctetString::new(vec![0; 20])).unwrap();
+        let config = DecoderConfig { max_input_len: 10, ..default };
+        match *check_limit::<OctetString>(&buf, config).kind() {
+            DecodeError::InputTooLong(10) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+        let config = DecoderConfig { max_element_len: 19, ..default };
+        match *check_limit::<OctetString>(&buf, config).kind() {
+            DecodeError::ElementTooLong(19) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+
+        let mut nested = Value::Null;
+        for _ in 0..5 {
+            nested = Value::Sequence(vec![nested]);
+        }
+        let buf = ::to_asn1(&nested).unwrap();
+        let config = DecoderConfig { max_depth: 4, ..default };
+        match *check_limit::<Value>(&buf, config).kind() {
+            DecodeError::NestingTooDeep(4) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+        let config = DecoderConfig { max_depth: 5, ..default };
+        let de = Deserializer::new(buf.as_slice()).with_config(config);
+        assert_eq!(Value::asn1_deserialize(de).unwrap(), nested);
+
+        let buf = ::to_asn1(&vec![1u8; 5]).unwrap();
+        let config = DecoderConfig { max_seq_len: 4, ..default };
+        match *check_limit::<Vec<u8>>(&buf, config).kind() {
+            DecodeError::TooManyElements(4) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+
+        let buf = ::to_asn1(&ObjectIdentifier::new(vec![1, 2, 840, 113549, 1, 1, 11])).unwrap();
+        let config = DecoderConfig { max_oid_arcs: 6, ..default };
+        match *check_limit::<ObjectIdentifier>(&buf, config).kind() {
+            DecodeError::TooManyArcs(6) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+        let config = DecoderConfig { max_oid_arcs: 7, ..default };
+        assert_eq!(::from_asn1::<ObjectIdentifier>(&buf).unwrap(),
+                   ObjectIdentifier::asn1_deserialize(Deserializer::new(buf.as_slice())
+                                                          .with_config(config))
+                           .unwrap());
+    }
+}
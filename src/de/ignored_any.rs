@@ -0,0 +1,82 @@
+use info;
+use de::{Asn1Deserialize, Asn1Deserializer, Asn1Error, Asn1Visitor};
+
+/// Value of any type skipped without decoding, like a component the schema does not model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IgnoredAny;
+
+asn1_typed!(IgnoredAny, info::TYPE_ANY);
+
+impl<'de> Asn1Deserialize<'de> for IgnoredAny {
+    fn asn1_deserialize<D: Asn1Deserializer<'de>>(deserializer: D) -> Result<Self, D::Err> {
+        struct IgnoredVisitor;
+        impl<'de> Asn1Visitor<'de> for IgnoredVisitor {
+            type Value = IgnoredAny;
+
+            fn visit_ignored<E: Asn1Error>(self) -> Result<Self::Value, E> {
+                Ok(IgnoredAny)
+            }
+        }
+        deserializer.deserialize_ignored_any(IgnoredVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IgnoredAny;
+    use de::{Asn1Deserialize, Asn1Deserializer, Asn1Visitor, SeqAccess};
+    use der::Deserializer;
+    use info::Asn1Typed;
+    use universal::OctetString;
+
+    /// First INTEGER of a SEQUENCE, later components are skipped.
+    #[derive(Debug, PartialEq)]
+    struct Head(i32);
+
+    impl Asn1Typed for Head {
+        fn asn1_type() -> &'static str {
+            "HEAD"
+        }
+    }
+
+    impl<'de> Asn1Deserialize<'de> for Head {
+        fn asn1_deserialize<D: Asn1Deserializer<'de>>(deserializer: D) -> Result<Self, D::Err> {
+            struct HeadVisitor;
+            impl<'de> Asn1Visitor<'de> for HeadVisitor {
+                type Value = Head;
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Err>
+                    where A: SeqAccess<'de>
+                {
+                    let head = seq.next_field()?;
+                    seq.skip_remaining()?;
+                    Ok(Head(head))
+                }
+            }
+            deserializer.deserialize_seq(HeadVisitor)
+        }
+    }
+
+    #[test]
+    fn skip_remaining() {
+        let buf = ::to_asn1(&vec![7i32, 8, 9]).unwrap();
+        assert_eq!(::from_asn1::<Head>(&buf).unwrap(), Head(7));
+        assert_eq!(Head::asn1_deserialize(Deserializer::from_reader(buf.as_slice())).unwrap(),
+                   Head(7));
+        assert_eq!(::from_asn1::<Head>(&::to_asn1(&vec![1i32]).unwrap()).unwrap(), Head(1));
+    }
+
+    #[test]
+    fn ignored_any() {
+        let value = vec![OctetString::new(vec![0; 100000])];
+        let buf = ::to_asn1(&value).unwrap();
+        assert_eq!(::from_asn1::<IgnoredAny>(&buf).unwrap(), IgnoredAny);
+
+        let mut de = Deserializer::from_reader(buf.as_slice());
+        IgnoredAny::asn1_deserialize(&mut de).unwrap();
+        assert_eq!(de.position(), buf.len());
+
+        // length past the end of the input
+        assert!(::from_asn1::<IgnoredAny>(&buf[..buf.len() - 1]).is_err());
+    }
+}
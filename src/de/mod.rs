@@ -1,3 +1,9 @@
+pub mod config;
 pub mod traits;
+pub mod registry;
+pub mod ignored_any;
+pub use self::config::DecoderConfig;
 pub use self::traits::*;
+pub use self::registry::{Registry, DecodeFn};
+pub use self::ignored_any::IgnoredAny;
 
@@ -0,0 +1,190 @@
+use lib::collections::BTreeMap;
+use lib::fmt;
+
+use universal::Any;
+use der::DecodeError;
+
+/// Decoder of an open type value registered for one key.
+pub type DecodeFn<T, E = DecodeError> = fn(&Any) -> Result<T, E>;
+
+/// Information object set: maps a key, usually an OBJECT IDENTIFIER or an INTEGER, to the
+/// decoder of the open type value it identifies (ANY DEFINED BY). Decoders fail with `E`, which
+/// is reported through the `Asn1Error` of the deserializer.
+pub struct Registry<K, T, E = DecodeError> {
+    decoders: BTreeMap<K, DecodeFn<T, E>>,
+    fallback: fn(Any) -> T,
+}
+
+impl<K: Ord, T, E> Registry<K, T, E> {
+    /// Create new empty registry. Values with unknown keys are passed to `fallback` as raw TLVs.
+    pub fn new(fallback: fn(Any) -> T) -> Self {
+        Registry {
+            decoders: BTreeMap::new(),
+            fallback: fallback,
+        }
+    }
+
+    /// Register decoder for `key`, replacing the previous one.
+    pub fn register(&mut self, key: K, decoder: DecodeFn<T, E>) -> &mut Self {
+        self.decoders.insert(key, decoder);
+        self
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.decoders.contains_key(key)
+    }
+
+    /// Decode open type `value` identified by `key`.
+    pub fn decode(&self, key: &K, value: Any) -> Result<T, E> {
+        match self.decoders.get(key) {
+            Some(decoder) => decoder(&value),
+            None => Ok((self.fallback)(value)),
+        }
+    }
+}
+
+impl<K: fmt::Debug, T, E> fmt::Debug for Registry<K, T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.decoders.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::Registry;
+    use info::{self, Asn1Typed};
+    use ser::{self, SeqSerializer};
+    use de::{self, Asn1Visitor, SeqAccess};
+    use der::DecodeError;
+    use universal::{Any, ObjectIdentifier};
+    use universal::test_helper::ser_deser;
+
+    #[derive(Debug, PartialEq, Clone)]
+    enum Params {
+        Absent(()),
+        Curve(ObjectIdentifier),
+        Unknown(Any),
+    }
+
+    impl ser::Asn1Serialize for Params {
+        fn asn1_serialize<S: ser::Asn1Serializer>(&self, s: S) -> Result<S::Ok, S::Err> {
+            match *self {
+                Params::Absent(ref v) => v.asn1_serialize(s),
+                Params::Curve(ref v) => v.asn1_serialize(s),
+                Params::Unknown(ref v) => v.asn1_serialize(s),
+            }
+        }
+    }
+
+    fn rsa() -> ObjectIdentifier {
+        ObjectIdentifier::new(vec![1, 2, 840, 113549, 1, 1, 1])
+    }
+
+    fn ec() -> ObjectIdentifier {
+        ObjectIdentifier::new(vec![1, 2, 840, 10045, 2, 1])
+    }
+
+    fn algorithms() -> Registry<ObjectIdentifier, Params> {
+        let mut registry = Registry::new(Params::Unknown);
+        registry
+            .register(rsa(), |v| v.decode().map(Params::Absent))
+            .register(ec(), |v| v.decode().map(Params::Curve));
+        registry
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
+    struct AlgorithmIdentifier {
+        algorithm: ObjectIdentifier,
+        parameters: Option<Params>,
+    }
+
+    asn1_info!(AlgorithmIdentifier => info::TAG_SEQUENCE, "AlgorithmIdentifier");
+
+    impl ser::Asn1Serialize for AlgorithmIdentifier {
+        fn asn1_serialize<S: ser::Asn1Serializer>(&self, s: S) -> Result<S::Ok, S::Err> {
+            let mut s = s.serialize_sequence()?;
+            s.serialize_field(&self.algorithm)?;
+            if let Some(ref parameters) = self.parameters {
+                s.serialize_field(parameters)?;
+            }
+            s.finish()
+        }
+    }
+
+    impl<'de> de::Asn1Deserialize<'de> for AlgorithmIdentifier {
+        fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                          -> Result<Self, D::Err> {
+            struct SeqVisitor;
+            impl<'de> Asn1Visitor<'de> for SeqVisitor {
+                type Value = AlgorithmIdentifier;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str(AlgorithmIdentifier::asn1_type())
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Err>
+                    where A: SeqAccess<'de>
+                {
+                    let algorithm = seq.next_field()?;
+                    let parameters = seq.next_optional_field_defined_by(&algorithms(),
+                                                                        &algorithm)?;
+                    Ok(AlgorithmIdentifier {
+                           algorithm: algorithm,
+                           parameters: parameters,
+                       })
+                }
+            }
+            deserializer.deserialize_seq(SeqVisitor)
+        }
+    }
+
+    #[test]
+    fn defined_by_known() {
+        let rsa = AlgorithmIdentifier {
+            algorithm: rsa(),
+            parameters: Some(Params::Absent(())),
+        };
+        assert_eq!(rsa, ser_deser(&rsa));
+
+        let ec = AlgorithmIdentifier {
+            algorithm: ec(),
+            parameters: Some(Params::Curve(ObjectIdentifier::new(vec![1, 2, 840, 10045, 3, 1, 7]))),
+        };
+        assert_eq!(ec, ser_deser(&ec));
+    }
+
+    #[test]
+    fn defined_by_unknown() {
+        let other = AlgorithmIdentifier {
+            algorithm: ObjectIdentifier::new(vec![1, 3, 101, 112]),
+            parameters: Some(Params::Unknown(Any::from_value(&vec![1u8, 2, 3]).unwrap())),
+        };
+        assert_eq!(other, ser_deser(&other));
+    }
+
+    #[test]
+    fn defined_by_absent() {
+        let ed25519 = AlgorithmIdentifier {
+            algorithm: ObjectIdentifier::new(vec![1, 3, 101, 112]),
+            parameters: None,
+        };
+        assert_eq!(ed25519, ser_deser(&ed25519));
+    }
+
+    #[test]
+    fn defined_by_mismatch() {
+        // EC parameters must be an OBJECT IDENTIFIER
+        let bad = AlgorithmIdentifier {
+            algorithm: ec(),
+            parameters: Some(Params::Absent(())),
+        };
+        let buf = ::to_asn1(&bad).unwrap();
+        let e = ::from_asn1::<AlgorithmIdentifier>(&buf).unwrap_err();
+        match *e.kind() {
+            DecodeError::Custom(ref msg) => assert!(!msg.contains("TagMismatch"), "{}", msg),
+            ref e => panic!("unexpected {:?}", e),
+        }
+    }
+}
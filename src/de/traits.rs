@@ -1,5 +1,7 @@
-use std::fmt;
+use lib::{fmt, String, ToOwned, Vec};
 use info::{Asn1Typed, Tag, Len};
+use universal::{Any, ObjectIdentifierRef};
+use super::{DecoderConfig, IgnoredAny, Registry};
 
 pub trait Asn1Error {
     fn custom<T>(msg: T) -> Self
@@ -15,11 +17,16 @@ pub trait Asn1Error {
 
 /// ASN.1 Asn1Deserialize trait allows for deserializing primitive and constructed ASN.1 encoded values into
 /// Rust type, enum or struct.
-pub trait Asn1Deserialize: Sized + Asn1Typed {
+pub trait Asn1Deserialize<'de>: Sized + Asn1Typed {
     /// Asn1Deserialize ASN.1 value.
-    fn asn1_deserialize<'de, D>(deserializer: D) -> Result<Self, D::Err> where D: Asn1Deserializer<'de>;
+    fn asn1_deserialize<D>(deserializer: D) -> Result<Self, D::Err> where D: Asn1Deserializer<'de>;
 }
 
+/// Type that can be deserialized without borrowing from the input.
+pub trait Asn1DeserializeOwned: for<'de> Asn1Deserialize<'de> {}
+
+impl<T> Asn1DeserializeOwned for T where T: for<'de> Asn1Deserialize<'de> {}
+
 
 pub trait Asn1Deserializer<'de> {
     type Err: Asn1Error;
@@ -63,6 +70,10 @@ pub trait Asn1Deserializer<'de> {
     fn deserialize_object_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>;
 
+    /// Deserialize a complete TLV of any type without interpreting its content.
+    fn deserialize_raw<V>(self, visitor: V) -> Result<V::Value, Self::Err>
+        where V: Asn1Visitor<'de>;
+
     fn deserialize_tagged(self, tag: Tag) -> Result<Self::ExplicitDeserializer, Self::Err>;
     fn deserialize_tagged_implicit(self, tag: Tag) -> Result<Self::ImplicitDeserializer, Self::Err>;
 
@@ -71,6 +82,60 @@ pub trait Asn1Deserializer<'de> {
 
     fn deserialize_choice<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>;
+
+    /// Deserialize a value of any type, calling the visitor method matching its tag.
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Err>
+        where V: Asn1Visitor<'de>;
+
+    /// Skip a value of any type, then call `visit_ignored`. By default the value is read as a
+    /// raw TLV, deserializers able to skip it using only its header should override this.
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Err>
+        where Self: Sized,
+              V: Asn1Visitor<'de>
+    {
+        struct IgnoredVisitor<V>(V);
+
+        impl<'de, V: Asn1Visitor<'de>> Asn1Visitor<'de> for IgnoredVisitor<V> {
+            type Value = V::Value;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                self.0.expecting(formatter)
+            }
+
+            fn visit_raw<E>(self, _tag: Tag, _header_len: usize, _raw: Vec<u8>)
+                            -> Result<Self::Value, E>
+                where E: Asn1Error
+            {
+                self.0.visit_ignored()
+            }
+        }
+
+        self.deserialize_raw(IgnoredVisitor(visitor))
+    }
+
+    /// Limits for values decoded later from their encoding, like `Lazy`. `None` if the
+    /// deserializer has no limits.
+    fn decoder_config(&self) -> Option<DecoderConfig> {
+        None
+    }
+
+    /// Nesting depth of the next value, counted against `DecoderConfig::max_depth` when it is
+    /// decoded later. `None` if the deserializer does not track it.
+    fn value_depth(&self) -> Option<usize> {
+        None
+    }
+
+    /// Offset of the next value in the input, for errors of values decoded later from their
+    /// encoding. `None` if the deserializer does not track it.
+    fn value_offset(&self) -> Option<usize> {
+        None
+    }
+
+    /// Deserialize `T` together with its complete encoding, like signed data whose exact bytes
+    /// are verified.
+    fn deserialize_captured<T>(self) -> Result<(T, Vec<u8>), Self::Err>
+        where Self: Sized,
+              T: Asn1DeserializeOwned;
 }
 
 
@@ -152,24 +217,89 @@ pub trait Asn1Visitor<'de>: Sized {
         Err(E::invalid_type("OBJECT IDENTIFIER"))
     }
 
+    /// Visit content octets of an OBJECT IDENTIFIER borrowed from the input.
+    fn visit_borrowed_object_identifier<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+        where E: Asn1Error
+    {
+        match ObjectIdentifierRef::from_bytes(v) {
+            Some(oid) => self.visit_object_identifier(oid.arcs().collect()),
+            None => Err(E::invalid_value("bad object identifier encoding")),
+        }
+    }
+
     fn visit_bit_string<E>(self, _v: (u8, Vec<u8>)) -> Result<Self::Value, E>
         where E: Asn1Error
     {
         Err(E::invalid_type("BIT STRING"))
     }
 
+    /// Visit BIT STRING with data borrowed from the input.
+    fn visit_borrowed_bit_string<E>(self, unused: u8, v: &'de [u8]) -> Result<Self::Value, E>
+        where E: Asn1Error
+    {
+        self.visit_bit_string((unused, v.to_vec()))
+    }
+
     fn visit_byte_string<E>(self, _v: Vec<u8>) -> Result<Self::Value, E>
         where E: Asn1Error
     {
         Err(E::invalid_type("BIT STRING"))
     }
 
+    /// Visit OCTET STRING borrowed from the input.
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+        where E: Asn1Error
+    {
+        self.visit_byte_string(v.to_vec())
+    }
+
+    /// Visit a character string or time value, `tag` tells which one it is.
+    fn visit_string<E>(self, _tag: Tag, _v: String) -> Result<Self::Value, E>
+        where E: Asn1Error
+    {
+        Err(E::invalid_type("character string"))
+    }
+
+    /// Visit a character string or time value borrowed from the input.
+    fn visit_borrowed_str<E>(self, tag: Tag, v: &'de str) -> Result<Self::Value, E>
+        where E: Asn1Error
+    {
+        self.visit_string(tag, v.to_owned())
+    }
+
+    /// Visit a complete encoded TLV, `raw[header_len..]` being its content.
+    fn visit_raw<E>(self, _tag: Tag, _header_len: usize, _raw: Vec<u8>) -> Result<Self::Value, E>
+        where E: Asn1Error
+    {
+        Err(E::invalid_type("ANY"))
+    }
+
+    /// Visit a value skipped without decoding it.
+    fn visit_ignored<E>(self) -> Result<Self::Value, E>
+        where E: Asn1Error
+    {
+        Err(E::invalid_type("ignored value"))
+    }
+
     fn visit_seq<A>(self, _seq: A) -> Result<Self::Value, A::Err>
         where A: SeqAccess<'de>
     {
         Err(Asn1Error::invalid_type("SEQUENCE or SEQUENCE OF"))
     }
 
+    fn visit_set<A>(self, _set: A) -> Result<Self::Value, A::Err>
+        where A: SeqAccess<'de>
+    {
+        Err(Asn1Error::invalid_type("SET or SET OF"))
+    }
+
+    /// Visit contents of a constructed value with APPLICATION, CONTEXT SPECIFIC or PRIVATE tag.
+    fn visit_tagged<A>(self, _tag: Tag, _contents: A) -> Result<Self::Value, A::Err>
+        where A: SeqAccess<'de>
+    {
+        Err(Asn1Error::invalid_type("tagged value"))
+    }
+
     fn visit_choice<A>(self, _tag: &Tag, _deserializer: A) -> Result<Self::Value, A::Err>
         where A: Asn1Deserializer<'de>
     {
@@ -181,13 +311,62 @@ pub trait VariantAccess<'de> {
     type Err: Asn1Error;
 
     fn peek_tag(self) -> Result<Tag, Self::Err>;
-    fn variant<V>(self) -> Result<V, Self::Err> where V: Asn1Deserialize;
+    fn variant<V>(self) -> Result<V, Self::Err> where V: Asn1Deserialize<'de>;
 }
 
 pub trait SeqAccess<'de> {
     type Err: Asn1Error;
 
-    fn next_field<V>(&mut self) -> Result<V, Self::Err> where V: Asn1Deserialize;
+    fn next_field<V>(&mut self) -> Result<V, Self::Err> where V: Asn1Deserialize<'de>;
     fn remaining(&self) -> u64;
+
+    /// Deserialize next field called `name`, the name is used to locate decoding errors.
+    fn next_named_field<V>(&mut self, name: &'static str) -> Result<V, Self::Err>
+        where V: Asn1Deserialize<'de>
+    {
+        let _ = name;
+        self.next_field()
+    }
+
+    /// Deserialize next field as an open type value whose type is identified by `key`
+    /// (ANY DEFINED BY), usually a previously deserialized field.
+    fn next_field_defined_by<K, T, E>(&mut self,
+                                      registry: &Registry<K, T, E>,
+                                      key: &K)
+                                      -> Result<T, Self::Err>
+        where K: Ord,
+              E: fmt::Display
+    {
+        let value: Any = self.next_field()?;
+        registry.decode(key, value).map_err(Asn1Error::custom)
+    }
+
+    /// Deserialize next field like `next_field_defined_by`, `None` if there are no fields left.
+    /// An OPTIONAL open type can have any tag, so it can be told absent only as the last field.
+    fn next_optional_field_defined_by<K, T, E>(&mut self,
+                                               registry: &Registry<K, T, E>,
+                                               key: &K)
+                                               -> Result<Option<T>, Self::Err>
+        where K: Ord,
+              E: fmt::Display
+    {
+        if self.remaining() == 0 {
+            return Ok(None);
+        }
+        self.next_field_defined_by(registry, key).map(Some)
+    }
+
+    /// Skip next field without decoding it.
+    fn skip_field(&mut self) -> Result<(), Self::Err> {
+        self.next_field::<IgnoredAny>().map(|_| ())
+    }
+
+    /// Skip all remaining fields, like unknown trailing components.
+    fn skip_remaining(&mut self) -> Result<(), Self::Err> {
+        while self.remaining() > 0 {
+            self.skip_field()?;
+        }
+        Ok(())
+    }
 }
 
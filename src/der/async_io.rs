@@ -0,0 +1,329 @@
+use std::cmp;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use io;
+use info::Len;
+use de::{Asn1DeserializeOwned, DecoderConfig};
+use ser::Asn1Serialize;
+
+use super::frame::DEFAULT_MAX_FRAME_LEN;
+use super::read::{self, ReadError};
+use super::reader::{Deserializer, DecodeError};
+use super::writer::EncodeError;
+
+/// Largest part of the content read at once, so a hostile length is not allocated up front.
+const CHUNK_LEN: usize = 8 * 1024;
+
+/// Read exactly one TLV of at most `DEFAULT_MAX_FRAME_LEN` bytes from `reader` and decode it.
+///
+/// The header is read byte by byte to never read past the value, so wrap unbuffered readers
+/// into a buffered one.
+pub fn from_async_reader<R, T>(reader: R) -> ReadValue<R, T>
+    where R: AsyncRead + Unpin,
+          T: Asn1DeserializeOwned
+{
+    let config = DecoderConfig { max_input_len: DEFAULT_MAX_FRAME_LEN, ..Default::default() };
+    from_async_reader_with_config(reader, config)
+}
+
+/// Read exactly one TLV from `reader` and decode it with the limits of `config`. Values longer
+/// than `max_input_len` fail with `DecodeError::FrameTooLarge` before their content is read.
+pub fn from_async_reader_with_config<R, T>(reader: R, config: DecoderConfig) -> ReadValue<R, T>
+    where R: AsyncRead + Unpin,
+          T: Asn1DeserializeOwned
+{
+    ReadValue {
+        reader: reader,
+        buf: Vec::new(),
+        total: None,
+        config: config,
+        _marker: PhantomData,
+    }
+}
+
+/// Encode `value` and write it to `writer`.
+pub fn to_async_writer<W, T>(writer: W, value: &T) -> WriteValue<W>
+    where W: AsyncWrite + Unpin,
+          T: Asn1Serialize
+{
+    let (buf, error) = match ::to_asn1(value) {
+        Ok(buf) => (buf, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    WriteValue {
+        writer: writer,
+        buf: buf,
+        pos: 0,
+        error: error,
+    }
+}
+
+/// Future of `from_async_reader`.
+#[derive(Debug)]
+pub struct ReadValue<R, T> {
+    reader: R,
+    buf: Vec<u8>,
+    /// Length of the whole TLV, once its header is read.
+    total: Option<usize>,
+    config: DecoderConfig,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> ReadValue<R, T> {
+    /// Parse the header read so far, `None` if it is incomplete.
+    fn parse_header(&self) -> Result<Option<usize>, DecodeError> {
+        let mut header = self.buf.as_slice();
+        match read::read_tag(&mut header).and_then(|_| read::read_len(&mut header)) {
+            Ok(Len::Def(len)) => {
+                let header_len = self.buf.len() - header.len();
+                match header_len.checked_add(len) {
+                    Some(total) if total > self.config.max_input_len => {
+                        Err(DecodeError::FrameTooLarge(total))
+                    }
+                    Some(total) => Ok(Some(total)),
+                    None => Err(DecodeError::FrameTooLarge(usize::max_value())),
+                }
+            }
+            Ok(Len::Indef) => Err(DecodeError::InvalidLength("indefinite length")),
+            Err(ReadError::IoError(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn eof(&self) -> DecodeError {
+        if self.buf.is_empty() {
+            DecodeError::IO(io::Error::new(io::ErrorKind::UnexpectedEof))
+        } else {
+            DecodeError::Truncated(0)
+        }
+    }
+}
+
+impl<R, T> Future for ReadValue<R, T>
+    where R: AsyncRead + Unpin,
+          T: Asn1DeserializeOwned
+{
+    type Output = Result<T, DecodeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        loop {
+            let total = match this.total {
+                Some(total) => total,
+                None => {
+                    if let Some(total) = this.parse_header()? {
+                        this.total = Some(total);
+                        continue;
+                    }
+
+                    let mut byte = [0u8; 1];
+                    match Pin::new(&mut this.reader).poll_read(cx, &mut byte) {
+                        Poll::Ready(Ok(0)) => return Poll::Ready(Err(this.eof())),
+                        Poll::Ready(Ok(_)) => this.buf.push(byte[0]),
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                    continue;
+                }
+            };
+
+            let filled = this.buf.len();
+            if filled == total {
+                let mut de = Deserializer::new(this.buf.as_slice()).with_config(this.config);
+                return Poll::Ready(de.decode());
+            }
+
+            this.buf.resize(filled + cmp::min(total - filled, CHUNK_LEN), 0);
+            let result = Pin::new(&mut this.reader).poll_read(cx, &mut this.buf[filled..]);
+            match result {
+                Poll::Ready(Ok(n)) if n > 0 => this.buf.truncate(filled + n),
+                Poll::Ready(Ok(_)) => {
+                    this.buf.truncate(filled);
+                    return Poll::Ready(Err(this.eof()));
+                }
+                Poll::Ready(Err(e)) => {
+                    this.buf.truncate(filled);
+                    return Poll::Ready(Err(e.into()));
+                }
+                Poll::Pending => {
+                    this.buf.truncate(filled);
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// Future of `to_async_writer`.
+#[derive(Debug)]
+pub struct WriteValue<W> {
+    writer: W,
+    buf: Vec<u8>,
+    pos: usize,
+    error: Option<EncodeError>,
+}
+
+impl<W: AsyncWrite + Unpin> Future for WriteValue<W> {
+    type Output = Result<(), EncodeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Err(e));
+        }
+
+        while this.pos < this.buf.len() {
+            match Pin::new(&mut this.writer).poll_write(cx, &this.buf[this.pos..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::WriteZero).into()))
+                }
+                Poll::Ready(Ok(n)) => this.pos += n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        match Pin::new(&mut this.writer).poll_flush(cx) {
+            Poll::Ready(result) => Poll::Ready(result.map_err(|e| e.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io as std_io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_executor::block_on;
+    use futures_io::{AsyncRead, AsyncWrite};
+
+    use super::{from_async_reader, from_async_reader_with_config, to_async_writer};
+    use de::DecoderConfig;
+    use der::DecodeError;
+    use universal::OctetString;
+
+    /// Reader returning at most `step` bytes per call, every other call is pending.
+    struct SlowReader<'a> {
+        data: &'a [u8],
+        step: usize,
+        pending: bool,
+    }
+
+    impl<'a> AsyncRead for SlowReader<'a> {
+        fn poll_read(mut self: Pin<&mut Self>,
+                     cx: &mut Context,
+                     buf: &mut [u8])
+                     -> Poll<std_io::Result<usize>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let n = *[self.step, buf.len(), self.data.len()].iter().min().unwrap();
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    /// Writer accepting at most 3 bytes per call.
+    struct SlowWriter(Vec<u8>);
+
+    impl AsyncWrite for SlowWriter {
+        fn poll_write(mut self: Pin<&mut Self>,
+                      _cx: &mut Context,
+                      buf: &[u8])
+                      -> Poll<std_io::Result<usize>> {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<std_io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<std_io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn async_round_trip() {
+        let first = OctetString::new((0..20000).map(|i| i as u8).collect());
+        let second = vec![1u32, 2, 3];
+
+        let mut writer = SlowWriter(Vec::new());
+        block_on(to_async_writer(&mut writer, &first)).unwrap();
+        block_on(to_async_writer(&mut writer, &second)).unwrap();
+
+        let mut reader = SlowReader {
+            data: &writer.0,
+            step: 5000,
+            pending: false,
+        };
+        assert_eq!(block_on(from_async_reader::<_, OctetString>(&mut reader)).unwrap(),
+                   first);
+        // nothing past the first value was read
+        assert_eq!(reader.data.len(), ::to_asn1(&second).unwrap().len());
+        assert_eq!(block_on(from_async_reader::<_, Vec<u32>>(&mut reader)).unwrap(),
+                   second);
+        match block_on(from_async_reader::<_, Vec<u32>>(&mut reader)) {
+            Err(DecodeError::IO(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn async_truncated() {
+        // 10 bytes announcing 1 TiB of content
+        let buf = [0x04, 0x86, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xaa, 0xbb];
+        let mut reader = SlowReader {
+            data: &buf,
+            step: 1,
+            pending: false,
+        };
+        let config = DecoderConfig::default();
+        match block_on(from_async_reader_with_config::<_, OctetString>(&mut reader, config)) {
+            Err(DecodeError::Truncated(0)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn async_too_large() {
+        let buf = [0x04, 0x86, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xaa, 0xbb];
+        let mut reader = SlowReader {
+            data: &buf,
+            step: 1,
+            pending: false,
+        };
+        match block_on(from_async_reader::<_, OctetString>(&mut reader)) {
+            Err(DecodeError::FrameTooLarge(0x0100_0000_0008)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        // the content was not read
+        assert_eq!(reader.data, &[0xaa, 0xbb]);
+
+        let buf = ::to_asn1(&vec![1u32, 2, 3]).unwrap();
+        let mut reader = SlowReader {
+            data: &buf,
+            step: 5,
+            pending: false,
+        };
+        let config = DecoderConfig { max_input_len: buf.len() - 1, ..Default::default() };
+        match block_on(from_async_reader_with_config::<_, Vec<u32>>(&mut reader, config)) {
+            Err(DecodeError::FrameTooLarge(len)) => assert_eq!(len, buf.len()),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use de::Asn1DeserializeOwned;
+use ser::Asn1Serialize;
+
+use super::frame::{to_definite, FrameScanner};
+pub use super::frame::DEFAULT_MAX_FRAME_LEN;
+use super::push::Status;
+use super::reader::{Deserializer, DecodeError};
+use super::writer::EncodeError;
+
+/// Codec framing a byte stream into top-level TLVs.
+///
+/// Frames are delimited by the outer tag and length, definite or indefinite (BER). Frames with
+/// indefinite lengths are converted to definite ones before decoding.
+#[derive(Debug)]
+pub struct Asn1Codec<T> {
+    max_frame_len: usize,
+    /// Scan of the frame being received, kept between calls.
+    scanner: FrameScanner,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Asn1Codec<T> {
+    pub fn new() -> Self {
+        Asn1Codec::with_max_frame_len(DEFAULT_MAX_FRAME_LEN)
+    }
+
+    /// Create codec rejecting frames longer than `max_frame_len` bytes.
+    pub fn with_max_frame_len(max_frame_len: usize) -> Self {
+        Asn1Codec {
+            max_frame_len: max_frame_len,
+            scanner: FrameScanner::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+}
+
+impl<T> Default for Asn1Codec<T> {
+    fn default() -> Self {
+        Asn1Codec::new()
+    }
+}
+
+impl<T: Asn1DeserializeOwned> Decoder for Asn1Codec<T> {
+    type Item = T;
+    type Error = DecodeError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, DecodeError> {
+        let len = match self.scanner.scan(src)? {
+            Status::Value(len) => len,
+            Status::NeedMore(n) => {
+                if src.len() + n > self.max_frame_len {
+                    self.scanner.reset();
+                    return Err(DecodeError::FrameTooLarge(src.len() + n));
+                }
+                src.reserve(n);
+                return Ok(None);
+            }
+        };
+        if len > self.max_frame_len {
+            // the frame is complete, so decoding can go on after it
+            src.advance(len);
+            return Err(DecodeError::FrameTooLarge(len));
+        }
+
+        let frame = src.split_to(len);
+        let frame = to_definite(&frame)?;
+        let mut de = Deserializer::new(frame.as_ref());
+        de.decode().map(Some)
+    }
+}
+
+impl<T: Asn1Serialize> Encoder<T> for Asn1Codec<T> {
+    type Error = EncodeError;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), EncodeError> {
+        let len = ::encoded_len(&item)?;
+        if len > self.max_frame_len {
+            return Err(EncodeError::FrameTooLarge(len));
+        }
+
+        let start = dst.len();
+        dst.resize(start + len, 0);
+        ::to_slice(&item, &mut dst[start..]).map(|_| ()).map_err(|e| {
+            dst.truncate(start);
+            e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::Asn1Codec;
+    use der::{DecodeError, EncodeError};
+    use ser::{self, Asn1Serialize, Asn1Serializer};
+    use universal::OctetString;
+
+    #[test]
+    fn loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let sender = thread::spawn(move || {
+            let mut codec = Asn1Codec::<Vec<u32>>::new();
+            let mut buf = BytesMut::new();
+            for i in 0..100u32 {
+                codec.encode(vec![i; i as usize % 7], &mut buf).unwrap();
+            }
+            // BER value with indefinite length
+            buf.extend_from_slice(&[0x30, 0x80, 0x02, 0x01, 0x07, 0x00, 0x00]);
+
+            let mut stream = TcpStream::connect(addr).unwrap();
+            for chunk in buf.chunks(13) {
+                stream.write_all(chunk).unwrap();
+            }
+        });
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut codec = Asn1Codec::<Vec<u32>>::new();
+        let mut buf = BytesMut::new();
+        let mut out = Vec::new();
+        let mut chunk = [0u8; 64];
+        loop {
+            let n = stream.read(&mut chunk).unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+            while let Some(v) = codec.decode(&mut buf).unwrap() {
+                out.push(v);
+            }
+            if n == 0 {
+                break;
+            }
+        }
+        sender.join().unwrap();
+
+        assert!(buf.is_empty());
+        assert_eq!(out.len(), 101);
+        for (i, v) in out.iter().take(100).enumerate() {
+            assert_eq!(*v, vec![i as u32; i % 7]);
+        }
+        assert_eq!(out[100], vec![7]);
+    }
+
+    #[test]
+    fn max_frame_len() {
+        let mut codec = Asn1Codec::<OctetString>::with_max_frame_len(100);
+        let mut buf = BytesMut::from(&[0x04, 0x82, 0x01, 0x00][..]);
+        match codec.decode(&mut buf) {
+            Err(DecodeError::FrameTooLarge(260)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // the oversized frame is skipped
+        let mut buf = BytesMut::from(&[0x04, 0x65][..]);
+        buf.extend_from_slice(&[0; 101]);
+        buf.extend_from_slice(&[0x04, 0x01, 0x07]);
+        match codec.decode(&mut buf) {
+            Err(DecodeError::FrameTooLarge(103)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(OctetString::new(vec![7])));
+
+        let mut buf = BytesMut::new();
+        match codec.encode(OctetString::new(vec![0; 100]), &mut buf) {
+            Err(EncodeError::FrameTooLarge(102)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        codec.encode(OctetString::new(vec![0; 90]), &mut buf).unwrap();
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(OctetString::new(vec![0; 90])));
+    }
+
+    #[test]
+    fn failed_encode() {
+        // fails once its length is known
+        struct Flaky(Cell<bool>);
+        impl Asn1Serialize for Flaky {
+            fn asn1_serialize<S: Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                if self.0.replace(true) {
+                    return Err(ser::Error::custom("second pass"));
+                }
+                serializer.serialize_u8(1)
+            }
+        }
+
+        let mut codec = Asn1Codec::<Flaky>::new();
+        let mut buf = BytesMut::from(&[0x05, 0x00][..]);
+        assert!(codec.encode(Flaky(Cell::new(false)), &mut buf).is_err());
+        assert_eq!(&buf[..], &[0x05, 0x00]);
+    }
+
+    #[test]
+    fn resumed_scan() {
+        // BER value with indefinite lengths, fed one byte at a time
+        let frame = [0x30, 0x80, 0x30, 0x03, 0x02, 0x01, 0x07, 0x30, 0x80, 0x02, 0x01, 0x08,
+                     0x00, 0x00, 0x00, 0x00];
+        let mut codec = Asn1Codec::<Vec<Vec<u32>>>::new();
+        let mut buf = BytesMut::new();
+        let mut out = Vec::new();
+        for &byte in frame.iter().chain(frame.iter()) {
+            buf.extend_from_slice(&[byte]);
+            if let Some(v) = codec.decode(&mut buf).unwrap() {
+                out.push(v);
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(out, vec![vec![vec![7], vec![8]], vec![vec![7], vec![8]]]);
+    }
+}
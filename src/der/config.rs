@@ -0,0 +1 @@
+pub use de::config::{DecoderConfig, DEFAULT_MAX_DEPTH};
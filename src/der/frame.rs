@@ -0,0 +1,321 @@
+use lib::{Cow, Vec};
+
+use super::push::Status;
+use super::reader::DecodeError;
+use super::sink::LengthPatcher;
+
+/// Default maximum frame size, 1 MiB.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 1 << 20;
+
+/// TLV header at the start of a buffer.
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    tag_len: usize,
+    header_len: usize,
+    /// Content length, `None` if indefinite.
+    len: Option<usize>,
+}
+
+fn parse_header(buf: &[u8]) -> Result<Status<Header>, DecodeError> {
+    if buf.is_empty() {
+        return Ok(Status::NeedMore(1));
+    }
+
+    let mut pos = 1;
+    if buf[0] & 0x1f == 0x1f {
+        loop {
+            match buf.get(pos) {
+                Some(&byte) => {
+                    pos += 1;
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                }
+                None => return Ok(Status::NeedMore(1)),
+            }
+        }
+    }
+    let tag_len = pos;
+
+    let head = match buf.get(pos) {
+        Some(&head) => head,
+        None => return Ok(Status::NeedMore(1)),
+    };
+    pos += 1;
+
+    let len = if head & 0x80 == 0 {
+        Some(head as usize)
+    } else if head == 0x80 {
+        None
+    } else {
+        let size = (head & 0x7f) as usize;
+        if size > 8 {
+            return Err(DecodeError::InvalidLength("length does not fit"));
+        }
+        if buf.len() < pos + size {
+            return Ok(Status::NeedMore(pos + size - buf.len()));
+        }
+
+        let len = buf[pos..pos + size].iter().fold(0u64, |len, &byte| len << 8 | byte as u64);
+        pos += size;
+        if len > (usize::max_value() - pos) as u64 {
+            return Err(DecodeError::InvalidLength("length does not fit"));
+        }
+        Some(len as usize)
+    };
+
+    Ok(Status::Value(Header {
+                         tag_len: tag_len,
+                         header_len: pos,
+                         len: len,
+                     }))
+}
+
+/// Length of the DER TLV at the start of `buf`, or the number of bytes missing to know it.
+///
+/// Missing bytes are exact once the length octets are known; before that a single byte is
+/// requested at a time.
+pub fn frame_len(buf: &[u8]) -> Result<Status<usize>, DecodeError> {
+    let header = match parse_header(buf)? {
+        Status::Value(header) => header,
+        Status::NeedMore(n) => return Ok(Status::NeedMore(n)),
+    };
+    let len = match header.len {
+        Some(len) => len,
+        None => return Err(DecodeError::InvalidLength("indefinite length")),
+    };
+
+    let end = header.header_len + len;
+    if buf.len() < end {
+        Ok(Status::NeedMore(end - buf.len()))
+    } else {
+        Ok(Status::Value(end))
+    }
+}
+
+/// Length of the BER TLV at the start of `buf`, which may use indefinite lengths.
+/// Missing bytes of a value with indefinite length are a lower bound.
+pub fn ber_frame_len(buf: &[u8]) -> Result<Status<usize>, DecodeError> {
+    FrameScanner::new().scan(buf)
+}
+
+/// Scanner of a BER TLV arriving in parts, resuming where the previous scan stopped so the
+/// input is parsed only once.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameScanner {
+    /// Number of open values with indefinite length.
+    depth: usize,
+    /// Start of the first value not scanned yet.
+    pos: usize,
+}
+
+impl FrameScanner {
+    pub fn new() -> Self {
+        FrameScanner::default()
+    }
+
+    /// Length of the BER TLV at the start of `buf`, like `ber_frame_len`. Until it is known,
+    /// `buf` must keep the bytes of previous scans, call `reset` before scanning a new frame.
+    pub fn scan(&mut self, buf: &[u8]) -> Result<Status<usize>, DecodeError> {
+        let result = self.resume(buf);
+        if let Ok(Status::NeedMore(_)) = result {
+            return result;
+        }
+        self.reset();
+        result
+    }
+
+    pub fn reset(&mut self) {
+        *self = FrameScanner::new();
+    }
+
+    fn resume(&mut self, buf: &[u8]) -> Result<Status<usize>, DecodeError> {
+        loop {
+            let pos = self.pos;
+            if self.depth > 0 && buf[pos..].starts_with(&[0, 0]) {
+                self.pos += 2;
+                self.depth -= 1;
+                if self.depth == 0 {
+                    return Ok(Status::Value(self.pos));
+                }
+                continue;
+            }
+
+            let header = match parse_header(&buf[pos..])? {
+                Status::Value(header) => header,
+                Status::NeedMore(n) => return Ok(Status::NeedMore(n)),
+            };
+
+            match header.len {
+                Some(len) => {
+                    let end = match (pos + header.header_len).checked_add(len) {
+                        Some(end) => end,
+                        None => return Err(DecodeError::InvalidLength("length does not fit")),
+                    };
+                    if buf.len() < end {
+                        return Ok(Status::NeedMore(end - buf.len()));
+                    } else if self.depth == 0 {
+                        return Ok(Status::Value(end));
+                    }
+                    self.pos = end;
+                }
+                None => {
+                    if buf[pos] & 0x20 == 0 {
+                        return Err(DecodeError::InvalidLength("indefinite length of primitive \
+                                                               value"));
+                    }
+                    self.depth += 1;
+                    self.pos += header.header_len;
+                }
+            }
+        }
+    }
+}
+
+/// Check if TLV `frame` has values with indefinite length.
+fn has_indefinite(frame: &[u8]) -> bool {
+    let mut pos = 0;
+    while pos < frame.len() {
+        match parse_header(&frame[pos..]) {
+            Ok(Status::Value(Header { len: None, .. })) => return true,
+            Ok(Status::Value(Header { header_len, len: Some(len), .. })) => {
+                // step into constructed values, over primitive ones
+                pos = if frame[pos] & 0x20 != 0 {
+                    pos + header_len
+                } else {
+                    (pos + header_len).saturating_add(len)
+                };
+            }
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// Re-encode BER TLV `frame` with definite lengths only, so the DER deserializer can decode it.
+/// Frames without indefinite lengths are returned as is.
+pub fn to_definite(frame: &[u8]) -> Result<Cow<[u8]>, DecodeError> {
+    if !has_indefinite(frame) {
+        return Ok(Cow::Borrowed(frame));
+    }
+
+    let mut out: Vec<u8> = Vec::with_capacity(frame.len());
+    let mut lengths = LengthPatcher::new();
+    // marks of open constructed values and their ends, if definite
+    let mut stack: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        match stack.last().cloned() {
+            Some((mark, Some(end))) if pos == end => {
+                lengths.close(&mut out, mark)?;
+                stack.pop();
+            }
+            Some((mark, None)) if frame[pos..].starts_with(&[0, 0]) => {
+                pos += 2;
+                lengths.close(&mut out, mark)?;
+                stack.pop();
+            }
+            _ => {
+                let header = match parse_header(&frame[pos..])? {
+                    Status::Value(header) => header,
+                    Status::NeedMore(_) => return Err(DecodeError::Truncated(pos)),
+                };
+                let content = pos + header.header_len;
+
+                if frame[pos] & 0x20 != 0 {
+                    out.extend_from_slice(&frame[pos..pos + header.tag_len]);
+                    let mark = lengths.open(&mut out)?;
+                    stack.push((mark, header.len.map(|len| content.saturating_add(len))));
+                    pos = content;
+                    continue;
+                }
+
+                let len = match header.len {
+                    Some(len) => len,
+                    None => {
+                        return Err(DecodeError::InvalidLength("indefinite length of primitive \
+                                                                value"))
+                    }
+                };
+                if frame.len() - content < len {
+                    return Err(DecodeError::Truncated(pos));
+                }
+                out.extend_from_slice(&frame[pos..content + len]);
+                pos = content + len;
+            }
+        }
+
+        match stack.last() {
+            None => return Ok(Cow::Owned(out)),
+            Some(&(_, Some(end))) if pos > end => {
+                return Err(DecodeError::InvalidLength("value exceeds enclosing value"))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{ber_frame_len, frame_len, to_definite, FrameScanner};
+    use der::{DecodeError, Status};
+
+    const NESTED: [u8; 14] =
+        [0x30, 0x80, 0x30, 0x80, 0x02, 0x01, 0x01, 0x00, 0x00, 0x04, 0x01, 0xaa, 0x00, 0x00];
+
+    #[test]
+    fn der_frame() {
+        assert_eq!(frame_len(&[0x04, 0x82, 0x01]).unwrap(), Status::NeedMore(1));
+        assert_eq!(frame_len(&[0x04, 0x82, 0x01, 0x00]).unwrap(), Status::NeedMore(256));
+        assert_eq!(frame_len(&[0x05, 0x00, 0x05]).unwrap(), Status::Value(2));
+        match frame_len(&NESTED) {
+            Err(DecodeError::InvalidLength(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ber_frame() {
+        assert_eq!(ber_frame_len(&NESTED).unwrap(), Status::Value(14));
+        assert_eq!(ber_frame_len(&NESTED[..9]).unwrap(), Status::NeedMore(1));
+        assert_eq!(ber_frame_len(&NESTED[..10]).unwrap(), Status::NeedMore(1));
+        assert_eq!(ber_frame_len(&NESTED[..13]).unwrap(), Status::NeedMore(1));
+        assert_eq!(ber_frame_len(&[0x02, 0x01, 0x05, 0x00]).unwrap(), Status::Value(3));
+
+        let mut scanner = FrameScanner::new();
+        for end in 0..NESTED.len() {
+            assert_eq!(scanner.scan(&NESTED[..end]).unwrap(),
+                       ber_frame_len(&NESTED[..end]).unwrap());
+        }
+        assert_eq!(scanner.scan(&NESTED).unwrap(), Status::Value(14));
+        assert_eq!(scanner.scan(&NESTED).unwrap(), Status::Value(14));
+        match ber_frame_len(&[0x04, 0x80]) {
+            Err(DecodeError::InvalidLength(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn definite() {
+        assert_eq!(to_definite(&NESTED).unwrap().as_ref(),
+                   &[0x30, 0x08, 0x30, 0x03, 0x02, 0x01, 0x01, 0x04, 0x01, 0xaa]);
+        // indefinite length inside of definite one
+        assert_eq!(to_definite(&[0x30, 0x06, 0x30, 0x80, 0x05, 0x00, 0x00, 0x00]).unwrap().as_ref(),
+                   &[0x30, 0x04, 0x30, 0x02, 0x05, 0x00]);
+
+        let v = vec![vec![1u32, 2], vec![]];
+        let buf = ::to_asn1(&v).unwrap();
+        match to_definite(&buf).unwrap() {
+            Cow::Borrowed(v) => assert_eq!(v, buf.as_slice()),
+            Cow::Owned(_) => panic!("definite frame copied"),
+        }
+
+        match to_definite(&NESTED[..12]) {
+            Err(DecodeError::Truncated(12)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
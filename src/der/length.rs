@@ -0,0 +1,251 @@
+use info::{self, Tag};
+use ser::{self, Asn1Serialize, SeqSerializer};
+
+use super::read;
+use super::write;
+use super::EncodeError;
+
+#[inline]
+fn tag_len(tag: &Tag) -> usize {
+    if tag.is_short() {
+        1
+    } else {
+        1 + write::base128_len(tag.tagnum() as u64)
+    }
+}
+
+#[inline]
+fn len_len(len: usize) -> usize {
+    let mut size = 1;
+    if len >= 0x80 {
+        while size < 8 && (len as u64) >> (8 * size) != 0 {
+            size += 1;
+        }
+        size += 1;
+    }
+    size
+}
+
+#[inline]
+fn tlv_len(tag: &Tag, content_len: usize) -> usize {
+    tag_len(tag) + len_len(content_len) + content_len
+}
+
+/// Content length of a signed INTEGER.
+#[inline]
+fn signed_len(value: i64) -> usize {
+    let mut len = 1;
+    while len < 8 && (value >> (8 * len - 1) != 0 && value >> (8 * len - 1) != -1) {
+        len += 1;
+    }
+    len
+}
+
+/// Content length of an unsigned INTEGER, including the leading zero octet.
+#[inline]
+fn unsigned_len(value: u64) -> usize {
+    let mut len = 1;
+    while len < 9 && value >> (8 * len - 1) != 0 {
+        len += 1;
+    }
+    len
+}
+
+/// Serializer computing the length of DER encoding without producing it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthSerializer {
+    implicit_tag: Option<Tag>,
+}
+
+impl LengthSerializer {
+    pub fn new() -> Self {
+        LengthSerializer { implicit_tag: None }
+    }
+
+    #[inline]
+    fn primitive(self, default_tag: Tag, content_len: usize) -> Result<usize, EncodeError> {
+        Ok(tlv_len(&self.implicit_tag.unwrap_or(default_tag), content_len))
+    }
+}
+
+/// Length serializer of SEQUENCE fields.
+#[derive(Debug)]
+pub struct LengthSeqSerializer {
+    tag: Tag,
+    content_len: usize,
+}
+
+impl SeqSerializer for LengthSeqSerializer {
+    type Ok = usize;
+    type Err = EncodeError;
+
+    fn serialize_field<V: Asn1Serialize + ?Sized>(&mut self, value: &V) -> Result<(), Self::Err> {
+        self.content_len += value.asn1_serialize(LengthSerializer::new())?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Ok, Self::Err> {
+        Ok(tlv_len(&self.tag, self.content_len))
+    }
+}
+
+impl ser::Asn1Serializer for LengthSerializer {
+    type Ok = usize;
+    type Err = EncodeError;
+
+    type SeqSerializer = LengthSeqSerializer;
+    type ImplicitSerializer = LengthSerializer;
+    type ExplicitSerializer = LengthSerializer;
+
+    fn serialize_bool(self, _value: bool) -> Result<Self::Ok, Self::Err> {
+        self.primitive(info::TAG_BOOLEAN, 1)
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Self::Ok, Self::Err> {
+        self.serialize_i64(value as i64)
+    }
+    fn serialize_i16(self, value: i16) -> Result<Self::Ok, Self::Err> {
+        self.serialize_i64(value as i64)
+    }
+    fn serialize_i32(self, value: i32) -> Result<Self::Ok, Self::Err> {
+        self.serialize_i64(value as i64)
+    }
+    fn serialize_i64(self, value: i64) -> Result<Self::Ok, Self::Err> {
+        self.primitive(info::TAG_INTEGER, signed_len(value))
+    }
+    fn serialize_isize(self, value: isize) -> Result<Self::Ok, Self::Err> {
+        self.serialize_i64(value as i64)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Self::Ok, Self::Err> {
+        self.serialize_u64(value as u64)
+    }
+    fn serialize_u16(self, value: u16) -> Result<Self::Ok, Self::Err> {
+        self.serialize_u64(value as u64)
+    }
+    fn serialize_u32(self, value: u32) -> Result<Self::Ok, Self::Err> {
+        self.serialize_u64(value as u64)
+    }
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok, Self::Err> {
+        self.primitive(info::TAG_INTEGER, unsigned_len(value))
+    }
+    fn serialize_usize(self, value: usize) -> Result<Self::Ok, Self::Err> {
+        self.serialize_u64(value as u64)
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Err> {
+        self.serialize_f64(value as f64)
+    }
+    fn serialize_f64(self, value: f64) -> Result<Self::Ok, Self::Err> {
+        self.primitive(info::TAG_REAL, write::real_len(value))
+    }
+
+    fn serialize_bit_string(self, value: (u8, &[u8])) -> Result<Self::Ok, Self::Err> {
+        let (unused, bytes) = value;
+        if unused < 8 {
+            self.primitive(info::TAG_BIT_STRING, bytes.len() + 1)
+        } else {
+            Err(EncodeError::InvalidValue)
+        }
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Err> {
+        self.primitive(info::TAG_OCTET_STRING, value.len())
+    }
+
+    fn serialize_null(self) -> Result<Self::Ok, Self::Err> {
+        self.primitive(info::TAG_NULL, 0)
+    }
+
+    fn serialize_object_identifier(self, value: &[u64]) -> Result<Self::Ok, Self::Err> {
+        let first = write::first_subidentifier(value).ok_or(EncodeError::InvalidValue)?;
+        let tail_len: usize = value[2..].iter().map(|&arc| write::base128_len(arc)).sum();
+        self.primitive(info::TAG_OBJECT_IDENTIFIER, write::base128_len(first) + tail_len)
+    }
+
+    fn serialize_raw(self, value: &[u8]) -> Result<Self::Ok, Self::Err> {
+        match self.implicit_tag {
+            None => Ok(value.len()),
+            Some(tag) => {
+                let mut content = value;
+                read::read_tag(&mut content).map_err(|_| EncodeError::InvalidValue)?;
+                let len = read::read_len_def(&mut content).map_err(|_| EncodeError::InvalidLength)?;
+                if len != content.len() {
+                    return Err(EncodeError::InvalidLength);
+                }
+                Ok(tlv_len(&tag, len))
+            }
+        }
+    }
+
+    fn serialize_tagged(self, tag: Tag) -> Result<Self::ExplicitSerializer, Self::Err> {
+        self.serialize_implicit(tag)
+    }
+
+    fn serialize_implicit(self, tag: Tag) -> Result<Self::ImplicitSerializer, Self::Err> {
+        Ok(LengthSerializer { implicit_tag: Some(tag) })
+    }
+
+    fn serialize_sequence(self) -> Result<Self::SeqSerializer, Self::Err> {
+        Ok(LengthSeqSerializer {
+               tag: self.implicit_tag.unwrap_or(info::TAG_SEQUENCE),
+               content_len: 0,
+           })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use info::{self, Tag};
+    use ser::Asn1Serialize;
+    use universal::{BitString, ObjectIdentifier, OctetString, Value};
+
+    fn check<T: Asn1Serialize + Debug>(value: &T) {
+        assert_eq!(::encoded_len(value).unwrap(),
+                   ::to_asn1(value).unwrap().len(),
+                   "{:?}",
+                   value);
+    }
+
+    #[quickcheck]
+    fn integer_len(v: i64, u: u64) -> bool {
+        ::encoded_len(&v).unwrap() == ::to_asn1(&v).unwrap().len() &&
+        ::encoded_len(&u).unwrap() == ::to_asn1(&u).unwrap().len()
+    }
+
+    #[test]
+    fn integer_bounds() {
+        for shift in 0..64 {
+            let u = 1u64 << shift;
+            for &v in [u - 1, u, u + 1].iter() {
+                check(&v);
+                check(&(v as i64));
+                check(&(v as i64).wrapping_neg());
+                check(&(v as u32));
+                check(&(v as i32));
+                check(&(v as u16));
+                check(&(v as i16));
+                check(&(v as u8));
+                check(&(v as i8));
+            }
+        }
+    }
+
+    #[test]
+    fn universal_len() {
+        check(&true);
+        check(&());
+        check(&1.5f64);
+        check(&-0.0f32);
+        check(&BitString::from_bits(&[true, false, true]));
+        check(&OctetString::new(vec![0; 300]));
+        check(&ObjectIdentifier::new(vec![1, 2, 840, 113549, 1, 1, 11]));
+        check(&vec![vec![0u8; 100], vec![1u8; 2]]);
+        check(&Value::Tagged {
+                   tag: Tag::constructed(info::ContextSpecific, 1000),
+                   contents: vec![Value::Utf8String("test".to_owned()), Value::Null],
+               });
+    }
+}
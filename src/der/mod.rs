@@ -1,8 +1,40 @@
+#[cfg(feature = "futures")]
+pub mod async_io;
+#[cfg(feature = "tokio")]
+pub mod codec;
+pub mod config;
+pub mod frame;
+pub mod length;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod push;
 mod read;
 pub mod reader;
+pub mod sink;
+pub mod source;
+pub mod stream;
+pub mod tlv;
 mod write;
 pub mod writer;
 
+#[cfg(feature = "futures")]
+pub use self::async_io::{from_async_reader, from_async_reader_with_config, to_async_writer,
+                         ReadValue, WriteValue};
+#[cfg(feature = "tokio")]
+pub use self::codec::Asn1Codec;
+pub use self::config::DecoderConfig;
+pub use self::length::{LengthSerializer, LengthSeqSerializer};
+#[cfg(feature = "rayon")]
+pub use self::parallel::{par_decode_seq_of, par_decode_seq_of_with_config, ElementError};
+pub use self::push::{PushDecoder, Status};
 pub use self::reader::*;
+pub use self::sink::{LengthPatcher, Sink, SliceSink};
+#[cfg(feature = "std")]
+pub use self::sink::IoSink;
+pub use self::source::Source;
+pub use self::stream::{StreamDecoder, StreamIter};
+pub use self::tlv::{TlvBuilder, TlvReader, Token};
+#[cfg(feature = "std")]
+pub use self::source::IoSource;
 pub use self::writer::*;
 
@@ -0,0 +1,192 @@
+//! Parallel decoding of SEQUENCE OF held in memory, for bulk processing of inputs like
+//! certificate transparency log dumps or big CRLs.
+use lib::{fmt, Vec};
+
+use rayon::prelude::*;
+
+use info::{self, Len};
+use de::Asn1Deserialize;
+
+use super::config::DecoderConfig;
+use super::reader::{DecodeError, Deserializer};
+use super::tlv::{TlvReader, Token};
+
+/// Error of parallel SEQUENCE OF decoding.
+#[derive(Debug)]
+pub struct ElementError {
+    /// Index of the first element that failed, `None` if the SEQUENCE OF itself is malformed.
+    pub index: Option<usize>,
+    /// Error located in the input, its path starts with `SEQUENCE OF[index]`.
+    pub error: DecodeError,
+}
+
+impl fmt::Display for ElementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl ::std::error::Error for ElementError {
+    fn source(&self) -> Option<&(dyn (::std::error::Error) + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Decode SEQUENCE OF `input` with elements decoded in parallel, see
+/// `par_decode_seq_of_with_config`.
+pub fn par_decode_seq_of<'de, T>(input: &'de [u8]) -> Result<Vec<T>, ElementError>
+    where T: Asn1Deserialize<'de> + Send
+{
+    par_decode_seq_of_with_config(input, DecoderConfig::default())
+}
+
+/// Decode SEQUENCE OF `input` by scanning the boundaries of its elements first and then
+/// decoding them in parallel. Elements keep their order and the error of the first failed
+/// element is reported.
+pub fn par_decode_seq_of_with_config<'de, T>(input: &'de [u8],
+                                             config: DecoderConfig)
+                                             -> Result<Vec<T>, ElementError>
+    where T: Asn1Deserialize<'de> + Send
+{
+    let bounds = element_bounds(input, &config)?;
+
+    let results: Vec<Result<T, DecodeError>> = bounds.par_iter()
+        .enumerate()
+        .map(|(index, &(start, end))| {
+            // elements are decoded one level below the SEQUENCE OF
+            let mut de = Deserializer::new(&input[start..end])
+                .with_config(config)
+                .with_depth(1);
+            T::asn1_deserialize(&mut de).map_err(|e| locate(e, Some(index), start))
+        })
+        .collect();
+
+    results.into_iter()
+        .enumerate()
+        .map(|(index, result)| {
+                 result.map_err(|e| {
+                                    ElementError {
+                                        index: Some(index),
+                                        error: e,
+                                    }
+                                })
+             })
+        .collect()
+}
+
+/// Scan the SEQUENCE OF header and the TLV framing of its elements.
+fn element_bounds(input: &[u8],
+                  config: &DecoderConfig)
+                  -> Result<Vec<(usize, usize)>, ElementError> {
+    let malformed = |e| {
+        ElementError {
+            index: None,
+            error: locate(e, None, 0),
+        }
+    };
+
+    if input.len() > config.max_input_len {
+        return Err(malformed(DecodeError::InputTooLong(config.max_input_len)));
+    }
+
+    let mut reader = TlvReader::new(input);
+    match reader.next_token().map_err(&malformed)? {
+        Some(Token::Start { tag, len: Len::Def(len), .. }) if tag == info::TAG_SEQUENCE => {
+            if len > config.max_element_len {
+                return Err(malformed(DecodeError::ElementTooLong(config.max_element_len)));
+            }
+        }
+        Some(Token::Start { len: Len::Indef, .. }) => {
+            return Err(malformed(DecodeError::InvalidLength("indefinite length")));
+        }
+        Some(Token::Start { tag, .. }) |
+        Some(Token::Primitive { tag, .. }) => {
+            return Err(malformed(DecodeError::TagMismatch(info::TAG_SEQUENCE, tag)));
+        }
+        Some(Token::End) | None => return Err(malformed(DecodeError::Truncated(0))),
+    }
+
+    let mut bounds = Vec::new();
+    loop {
+        let index = bounds.len();
+        let start = reader.position();
+        match reader.skip() {
+            Ok(true) if index == config.max_seq_len => {
+                let e = DecodeError::TooManyElements(config.max_seq_len);
+                return Err(ElementError {
+                               index: Some(index),
+                               error: locate(e, Some(index), start),
+                           });
+            }
+            Ok(true) => bounds.push((start, reader.position())),
+            Ok(false) => return Ok(bounds),
+            Err(e) => {
+                return Err(ElementError {
+                               index: Some(index),
+                               error: locate(e, Some(index), start),
+                           })
+            }
+        }
+    }
+}
+
+/// Locate error `e` of element `index` starting at `start`, the SEQUENCE OF itself if `None`.
+fn locate(e: DecodeError, index: Option<usize>, start: usize) -> DecodeError {
+    let e = match index {
+        Some(index) => e.shifted(start).within(start, &format!("[{}]", index)),
+        None => e,
+    };
+    e.within(0, info::TYPE_SEQUENCE_OF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::par_decode_seq_of;
+    use der::{DecodeError, TlvBuilder};
+    use universal::OctetString;
+
+    #[test]
+    fn par_decode() {
+        let values: Vec<OctetString> = (0..1000u32)
+            .map(|i| OctetString::new(vec![i as u8; (i % 300) as usize]))
+            .collect();
+        let buf = ::to_asn1(&values).unwrap();
+        assert_eq!(par_decode_seq_of::<OctetString>(&buf).unwrap(), values);
+        assert_eq!(par_decode_seq_of::<u8>(&::to_asn1(&Vec::<u8>::new()).unwrap()).unwrap(),
+                   Vec::<u8>::new());
+    }
+
+    #[test]
+    fn par_decode_errors() {
+        // elements 2 and 4 are out of range, the first one is reported
+        let buf = ::to_asn1(&vec![1u32, 2, 300, 3, 400]).unwrap();
+        let e = par_decode_seq_of::<u8>(&buf).unwrap_err();
+        assert_eq!(e.index, Some(2));
+        assert_eq!(e.error.path(), Some("SEQUENCE OF[2]"));
+        assert_eq!(e.error.offset(), Some(8));
+
+        // paths and offsets match those of sequential decoding
+        let buf = ::to_asn1(&vec![vec![1u32], vec![2, 300]]).unwrap();
+        let e = par_decode_seq_of::<Vec<u8>>(&buf).unwrap_err();
+        let expected = ::from_asn1::<Vec<Vec<u8>>>(&buf).unwrap_err();
+        assert_eq!(e.error.path(), Some("SEQUENCE OF[1][1]"));
+        assert_eq!(e.error.path(), expected.path());
+        assert_eq!(e.error.offset(), expected.offset());
+
+        // framing of element 1 exceeds the SEQUENCE OF
+        let mut b = TlvBuilder::new();
+        b.sequence(|b| {
+                       b.integer(1).raw(&[0x02, 0x05, 0x00]);
+                   });
+        let e = par_decode_seq_of::<u8>(b.as_slice()).unwrap_err();
+        assert_eq!(e.index, Some(1));
+        assert_eq!(e.error.offset(), Some(5));
+
+        let e = par_decode_seq_of::<u8>(&::to_asn1(&1u32).unwrap()).unwrap_err();
+        assert_eq!(e.index, None);
+        match *e.error.kind() {
+            DecodeError::TagMismatch(..) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use lib::{cmp, marker, Vec};
+
+use de::Asn1DeserializeOwned;
+
+use super::frame::{frame_len, DEFAULT_MAX_FRAME_LEN};
+use super::reader::{Deserializer, DecodeError};
+
+/// Outcome of `PushDecoder::feed`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status<T> {
+    /// Complete value.
+    Value(T),
+    /// Number of bytes missing before anything can be decoded.
+    NeedMore(usize),
+}
+
+/// Incremental decoder of consecutive values, for input arriving in arbitrary chunks.
+///
+/// Bytes are buffered until the TLV headers show a value is complete, so it never blocks
+/// and never decodes the same input twice. A value failing to decode is dropped, so the
+/// following values are still decoded. Values longer than the maximum frame length are
+/// dropped as their bytes arrive, without buffering them.
+#[derive(Debug)]
+pub struct PushDecoder<T> {
+    buf: Vec<u8>,
+    max_frame_len: usize,
+    /// Bytes of a rejected frame still to be dropped.
+    discard: usize,
+    _marker: marker::PhantomData<T>,
+}
+
+impl<T: Asn1DeserializeOwned> PushDecoder<T> {
+    pub fn new() -> Self {
+        PushDecoder::with_max_frame_len(DEFAULT_MAX_FRAME_LEN)
+    }
+
+    /// Create decoder rejecting values longer than `max_frame_len` bytes.
+    pub fn with_max_frame_len(max_frame_len: usize) -> Self {
+        PushDecoder {
+            buf: Vec::new(),
+            max_frame_len: max_frame_len,
+            discard: 0,
+            _marker: marker::PhantomData,
+        }
+    }
+
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Number of bytes buffered and not decoded yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Append `data` to the input and decode the next value if it is complete. Call it again
+    /// with empty `data` to get further values already buffered.
+    ///
+    /// Values longer than the maximum are reported as `DecodeError::FrameTooLarge`. Malformed
+    /// headers lose track of the value boundaries, so the buffered input is dropped with them.
+    pub fn feed(&mut self, data: &[u8]) -> Result<Status<T>, DecodeError> {
+        let skipped = cmp::min(self.discard, data.len());
+        self.discard -= skipped;
+        if self.discard > 0 {
+            return Ok(Status::NeedMore(self.discard));
+        }
+        self.buf.extend_from_slice(&data[skipped..]);
+
+        let len = match frame_len(&self.buf) {
+            Ok(Status::Value(len)) => len,
+            Ok(Status::NeedMore(n)) => {
+                let end = self.buf.len() + n;
+                if end > self.max_frame_len {
+                    return Err(self.reject(end));
+                }
+                return Ok(Status::NeedMore(n));
+            }
+            Err(e) => {
+                self.buf.clear();
+                return Err(e);
+            }
+        };
+        if len > self.max_frame_len {
+            return Err(self.reject(len));
+        }
+
+        let result = Deserializer::new(&self.buf[..len]).decode();
+        self.buf.drain(..len);
+        result.map(Status::Value)
+    }
+
+    /// Drop the value of length `len` at the start of the buffer, with its bytes yet to come.
+    fn reject(&mut self, len: usize) -> DecodeError {
+        let buffered = cmp::min(len, self.buf.len());
+        self.buf.drain(..buffered);
+        self.discard = len - buffered;
+        DecodeError::FrameTooLarge(len)
+    }
+}
+
+impl<T: Asn1DeserializeOwned> Default for PushDecoder<T> {
+    fn default() -> Self {
+        PushDecoder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PushDecoder, Status};
+    use der::DecodeError;
+    use universal::OctetString;
+
+    #[test]
+    fn push_byte_by_byte() {
+        let v = OctetString::new(vec![0x55; 300]);
+        let buf = ::to_asn1(&v).unwrap();
+
+        let mut decoder = PushDecoder::<OctetString>::new();
+        assert_eq!(decoder.feed(&buf[..1]).unwrap(), Status::NeedMore(1));
+        // 0x82: two length octets follow
+        assert_eq!(decoder.feed(&buf[1..2]).unwrap(), Status::NeedMore(2));
+        assert_eq!(decoder.feed(&buf[2..3]).unwrap(), Status::NeedMore(1));
+        assert_eq!(decoder.feed(&buf[3..4]).unwrap(), Status::NeedMore(300));
+        assert_eq!(decoder.feed(&buf[4..200]).unwrap(), Status::NeedMore(104));
+        assert_eq!(decoder.feed(&buf[200..]).unwrap(), Status::Value(v));
+        assert_eq!(decoder.buffered(), 0);
+        assert_eq!(decoder.feed(&[]).unwrap(), Status::NeedMore(1));
+    }
+
+    #[test]
+    fn push_chunks() {
+        let mut buf = Vec::new();
+        for i in 0..3u32 {
+            buf.extend(::to_asn1(&vec![i; i as usize]).unwrap());
+        }
+
+        let mut decoder = PushDecoder::<Vec<u32>>::new();
+        assert_eq!(decoder.feed(&buf[..3]).unwrap(), Status::Value(vec![]));
+        assert_eq!(decoder.feed(&buf[3..]).unwrap(), Status::Value(vec![1]));
+        assert_eq!(decoder.feed(&[]).unwrap(), Status::Value(vec![2, 2]));
+        assert_eq!(decoder.feed(&[]).unwrap(), Status::NeedMore(1));
+    }
+
+    #[test]
+    fn push_long_tag() {
+        let mut decoder = PushDecoder::<::Value>::new();
+        assert_eq!(decoder.feed(&[0x7f, 0x81]).unwrap(), Status::NeedMore(1));
+        assert_eq!(decoder.feed(&[0x00]).unwrap(), Status::NeedMore(1));
+        assert_eq!(decoder.feed(&[0x02, 0x05]).unwrap(), Status::NeedMore(1));
+        match decoder.feed(&[0x00]).unwrap() {
+            Status::Value(_) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn push_indefinite() {
+        let mut decoder = PushDecoder::<::Value>::new();
+        match decoder.feed(&[0x30, 0x80]) {
+            Err(DecodeError::InvalidLength(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn push_recovers() {
+        // value failing to decode is dropped
+        let mut decoder = PushDecoder::<u8>::new();
+        let mut buf = ::to_asn1(&300u32).unwrap();
+        buf.extend(::to_asn1(&7u8).unwrap());
+        assert!(decoder.feed(&buf).is_err());
+        assert_eq!(decoder.feed(&[]).unwrap(), Status::Value(7));
+
+        // value over the maximum is dropped as it arrives
+        let mut decoder = PushDecoder::<OctetString>::with_max_frame_len(100);
+        let mut buf = ::to_asn1(&OctetString::new(vec![1; 256])).unwrap();
+        buf.extend(::to_asn1(&OctetString::new(vec![2; 10])).unwrap());
+        match decoder.feed(&buf[..10]) {
+            Err(DecodeError::FrameTooLarge(260)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(decoder.buffered(), 0);
+        assert_eq!(decoder.feed(&buf[10..200]).unwrap(), Status::NeedMore(60));
+        assert_eq!(decoder.feed(&buf[200..]).unwrap(),
+                   Status::Value(OctetString::new(vec![2; 10])));
+    }
+}
@@ -1,7 +1,10 @@
-use std::io::{Result as IoResult, Read as IoRead, Error as IoError};
+use lib::{mem, Cow, String, Vec};
+use io::{Result as IoResult, Read as IoRead, Error as IoError, ErrorKind as IoErrorKind};
 
 use info::{Tag, Len, LenNum};
 
+use super::source::Source;
+
 #[derive(Debug)]
 pub enum ReadError {
     InvalidTag,
@@ -22,6 +25,96 @@ pub fn read_byte<R: IoRead>(r: &mut R) -> IoResult<u8> {
     Ok(buf[0])
 }
 
+/// Reader adapter that keeps a copy of every byte read through it.
+pub struct Recorder<'a, R: 'a> {
+    inner: &'a mut R,
+    buf: &'a mut Vec<u8>,
+}
+
+impl<'a, R: IoRead> Recorder<'a, R> {
+    pub fn new(inner: &'a mut R, buf: &'a mut Vec<u8>) -> Self {
+        Recorder {
+            inner: inner,
+            buf: buf,
+        }
+    }
+}
+
+impl<'a, R: IoRead> IoRead for Recorder<'a, R> {
+    fn read_exact(&mut self, out: &mut [u8]) -> IoResult<()> {
+        self.inner.read_exact(out)?;
+        self.buf.extend_from_slice(out);
+        Ok(())
+    }
+}
+
+/// Reader adapter that counts bytes read and stops at the end of the enclosing value.
+#[derive(Debug)]
+pub struct Limited<R> {
+    inner: R,
+    pos: usize,
+    limit: Option<usize>,
+}
+
+impl<R> Limited<R> {
+    pub fn new(inner: R) -> Self {
+        Limited {
+            inner: inner,
+            pos: 0,
+            limit: None,
+        }
+    }
+
+    /// Number of bytes read so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of bytes left before the limit, if any.
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        self.limit.map(|limit| limit - self.pos)
+    }
+
+    /// Set new limit, returning the previous one.
+    #[inline]
+    pub fn set_limit(&mut self, limit: Option<usize>) -> Option<usize> {
+        mem::replace(&mut self.limit, limit)
+    }
+}
+
+impl<R: IoRead> IoRead for Limited<R> {
+    fn read_exact(&mut self, out: &mut [u8]) -> IoResult<()> {
+        if self.remaining().map_or(false, |remaining| out.len() > remaining) {
+            return Err(IoError::new(IoErrorKind::UnexpectedEof));
+        }
+        self.inner.read_exact(out)?;
+        self.pos += out.len();
+        Ok(())
+    }
+}
+
+impl<'de, S: Source<'de>> Source<'de> for Limited<S> {
+    fn read_bytes(&mut self, len: usize) -> IoResult<Cow<'de, [u8]>> {
+        if self.remaining().map_or(false, |remaining| len > remaining) {
+            return Err(IoError::new(IoErrorKind::UnexpectedEof));
+        }
+        let bytes = self.inner.read_bytes(len)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip_bytes(&mut self, len: usize) -> IoResult<()> {
+        if self.remaining().map_or(false, |remaining| len > remaining) {
+            return Err(IoError::new(IoErrorKind::UnexpectedEof));
+        }
+        self.inner.skip_bytes(len)?;
+        self.pos += len;
+        Ok(())
+    }
+}
+
 macro_rules! read_integer {
     ($ident:ident: $ty:ty, $($args:tt)*) => {
         #[inline]
@@ -36,8 +129,6 @@ macro_rules! read_integer {
             read_byte($reader).map(|out| out as i8 as $ty).map_err(|e| e.into())
         } else if $len <= $size {
             fn _read_transmute<R: IoRead>(r: &mut R, len: usize) -> IoResult<$ty> {
-                use std::mem;
-
                 let mut out: [u8; $size] = [0; $size];
                 let offset = $size - len;
                 r.read_exact(&mut out[offset..])?;
@@ -54,8 +145,6 @@ macro_rules! read_integer {
     };
     (__impl $ty:ty, $reader:expr, $len:expr, $size:expr, false) => {
         {
-            use std::mem;
-
             fn _read_transmute<R: IoRead>(r: &mut R, len: usize) -> IoResult<$ty> {
                 let mut out: [u8; $size] = [0; $size];
                 let offset = $size - len;
@@ -98,13 +187,152 @@ read_integer!(read_u64: u64, 8, false);
 read_integer!(read_usize: usize, 8, false);
 
 #[inline]
-pub fn read_f32<R: IoRead>(_r: &mut R, _len: usize) -> Result<f32, ReadError> {
-    unimplemented!()
+pub fn read_f32<R: IoRead>(r: &mut R, len: usize) -> Result<f32, ReadError> {
+    read_f64(r, len).map(|v| v as f32)
 }
 
+/// Power of two for `-1022 <= exp <= 1023`, `powi` is not available without `std`.
 #[inline]
-pub fn read_f64<R: IoRead>(_r: &mut R, _len: usize) -> Result<f64, ReadError> {
-    unimplemented!()
+fn pow2(exp: i32) -> f64 {
+    f64::from_bits(((1023 + exp) as u64) << 52)
+}
+
+/// Largest binary exponent of a REAL applied, in either direction.
+const MAX_REAL_SHIFT: i64 = 2200;
+
+pub fn read_f64<R: IoRead>(r: &mut R, len: usize) -> Result<f64, ReadError> {
+    use lib::f64;
+
+    if len == 0 {
+        return Ok(0.0);
+    }
+
+    let head = read_byte(r)?;
+
+    if head & 0x80 != 0 {
+        // binary encoding
+        let base_shift = match (head >> 4) & 0x03 {
+            0 => 1,
+            1 => 3,
+            2 => 4,
+            _ => return Err(ReadError::InvalidValue),
+        };
+        let scale = ((head >> 2) & 0x03) as i64;
+
+        let mut remaining = len - 1;
+        let exp_len = if head & 0x03 == 0x03 {
+            if remaining == 0 {
+                return Err(ReadError::InvalidLength);
+            }
+            remaining -= 1;
+            read_byte(r)? as usize
+        } else {
+            (head & 0x03) as usize + 1
+        };
+
+        if exp_len == 0 || exp_len > 4 || exp_len >= remaining {
+            return Err(ReadError::InvalidLength);
+        }
+        let exponent = read_i32(r, exp_len)? as i64;
+        remaining -= exp_len;
+
+        if remaining > 8 {
+            return Err(ReadError::InvalidValue);
+        }
+        let mantissa = read_u64(r, remaining)?;
+
+        // beyond this any non-zero mantissa overflows or underflows anyway, so the
+        // loops below run at most three times
+        let shift = exponent * base_shift + scale;
+        let mut shift = shift.clamp(-MAX_REAL_SHIFT, MAX_REAL_SHIFT);
+        let mut value = mantissa as f64;
+        while shift > 1000 {
+            value *= pow2(1000);
+            shift -= 1000;
+        }
+        while shift < -1000 {
+            value *= pow2(-1000);
+            shift += 1000;
+        }
+        value *= pow2(shift as i32);
+
+        Ok(if head & 0x40 != 0 { -value } else { value })
+    } else if head & 0x40 != 0 {
+        // special real value
+        if len != 1 {
+            return Err(ReadError::InvalidLength);
+        }
+        match head {
+            0x40 => Ok(f64::INFINITY),
+            0x41 => Ok(f64::NEG_INFINITY),
+            0x42 => Ok(f64::NAN),
+            0x43 => Ok(-0.0),
+            _ => Err(ReadError::InvalidValue),
+        }
+    } else {
+        // decimal encoding, ISO 6093 NR1, NR2 or NR3 form
+        let form = head & 0x3f;
+        if form == 0 || form > 3 {
+            return Err(ReadError::InvalidValue);
+        }
+        let mut buf = Vec::new();
+        read_into(r, len - 1, &mut buf)?;
+
+        read_decimal(form, &buf).ok_or(ReadError::InvalidValue)
+    }
+}
+
+/// Parse decimal REAL `buf` of ISO 6093 form `form`: leading spaces and sign, digits, `.` or
+/// `,` as decimal mark for NR2 and NR3 and an exponent for NR3, like ` -1,5E+3`.
+fn read_decimal(form: u8, buf: &[u8]) -> Option<f64> {
+    fn digits(buf: &[u8], pos: &mut usize) -> usize {
+        let start = *pos;
+        while *pos < buf.len() && buf[*pos].is_ascii_digit() {
+            *pos += 1;
+        }
+        *pos - start
+    }
+
+    fn sign(buf: &[u8], pos: &mut usize) {
+        if *pos < buf.len() && (buf[*pos] == b'+' || buf[*pos] == b'-') {
+            *pos += 1;
+        }
+    }
+
+    let mut pos = 0;
+    while pos < buf.len() && buf[pos] == b' ' {
+        pos += 1;
+    }
+    let start = pos;
+    sign(buf, &mut pos);
+    let mut n = digits(buf, &mut pos);
+    if form > 1 {
+        if pos == buf.len() || (buf[pos] != b'.' && buf[pos] != b',') {
+            return None;
+        }
+        pos += 1;
+        n += digits(buf, &mut pos);
+    }
+    if n == 0 {
+        return None;
+    }
+    if form == 3 {
+        if pos == buf.len() || (buf[pos] != b'E' && buf[pos] != b'e') {
+            return None;
+        }
+        pos += 1;
+        sign(buf, &mut pos);
+        if digits(buf, &mut pos) == 0 {
+            return None;
+        }
+    }
+    if pos != buf.len() {
+        return None;
+    }
+
+    // only ASCII is left, so this can not fail
+    let s: String = buf[start..].iter().map(|&b| if b == b',' { '.' } else { b as char }).collect();
+    s.parse().ok()
 }
 
 #[inline]
@@ -141,7 +369,6 @@ pub fn read_tag<R: IoRead>(r: &mut R) -> Result<Tag, ReadError> {
 }
 
 #[inline]
-#[allow(dead_code)]
 pub fn read_len<R: IoRead>(r: &mut R) -> Result<Len, ReadError> {
     let head = read_byte(r)?;
     let len = (head & 0x7f) as usize;
@@ -169,7 +396,6 @@ pub fn read_len_def<R: IoRead>(r: &mut R) -> Result<LenNum, ReadError> {
 }
 
 #[inline]
-#[allow(dead_code)]
 pub fn read_header<R: IoRead>(r: &mut R) -> Result<(Tag, Len), ReadError> {
     let tag = read_tag(r)?;
     let len = read_len(r)?;
@@ -186,54 +412,101 @@ pub fn read_boolean<R: IoRead>(r: &mut R, len: LenNum) -> Result<bool, ReadError
     }
 }
 
-pub fn read_bit_string<R: IoRead>(r: &mut R, len: LenNum) -> Result<(u8, Vec<u8>), ReadError> {
+/// Read the unused bits count of BIT STRING with content length `len`.
+pub fn read_unused_bits<R: IoRead>(r: &mut R, len: LenNum) -> Result<u8, ReadError> {
     if len == 0 {
         return Err(ReadError::InvalidLength);
     }
 
     let unused: u8 = read_u8(r, 1)?;
-    if unused == 0 && len == 1 {
-        return Ok((unused, vec![]));
-    } else if unused > 7 || (unused > 0 && len == 1) {
-        return Err(ReadError::InvalidValue);
+    if unused > 7 || (unused > 0 && len == 1) {
+        Err(ReadError::InvalidValue)
+    } else {
+        Ok(unused)
     }
+}
 
-    let mut buf: Vec<u8> = Vec::with_capacity(len);
-    r.take((len - 1) as u64).read_to_end(&mut buf)?;
-
-    Ok((unused, buf))
+/// Largest part of a value read at once, so the buffer grows with the input actually read
+/// and not with the length announced by its header.
+const CHUNK_LEN: usize = 8 * 1024;
+
+/// Read `len` bytes appending them to `buf`.
+pub fn read_into<R: IoRead>(r: &mut R, len: LenNum, buf: &mut Vec<u8>) -> IoResult<()> {
+    let mut remaining = len;
+    while remaining > 0 {
+        let start = buf.len();
+        let n = if remaining < CHUNK_LEN { remaining } else { CHUNK_LEN };
+        buf.resize(start + n, 0);
+        r.read_exact(&mut buf[start..])?;
+        remaining -= n;
+    }
+    Ok(())
 }
 
-pub fn read_octet_string<R: IoRead>(r: &mut R, len: LenNum) -> Result<Vec<u8>, ReadError> {
-    if len == 0 {
-        return Ok(Vec::new());
+/// Read and drop `len` bytes.
+pub fn skip<R: IoRead + ?Sized>(r: &mut R, len: LenNum) -> IoResult<()> {
+    let mut buf = [0u8; 512];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = if remaining < buf.len() { remaining } else { buf.len() };
+        r.read_exact(&mut buf[..n])?;
+        remaining -= n;
     }
+    Ok(())
+}
 
-    let mut buf: Vec<u8> = Vec::with_capacity(len);
-    r.take(len as u64).read_to_end(&mut buf)?;
+pub fn read_octet_string<R: IoRead>(r: &mut R, len: LenNum) -> IoResult<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::new();
+    read_into(r, len, &mut buf)?;
     Ok(buf)
 }
 
 pub fn read_object_identifier<R: IoRead>(r: &mut R, len: LenNum) -> Result<Vec<u64>, ReadError> {
+    let content = read_octet_string(r, len)?;
+    let mut nested = content.as_slice();
     let mut buf: Vec<u64> = Vec::new();
-    let mut nested = r.take(len as u64);
-
-    let i0 = read_byte(&mut nested)?;
 
-    buf.push((i0 / 40) as u64);
-    buf.push((i0 % 40) as u64);
+    // the first subidentifier holds two arcs
+    let first = read_subidentifier(&mut nested)?;
+    if first < 80 {
+        buf.push(first / 40);
+        buf.push(first % 40);
+    } else {
+        buf.push(2);
+        buf.push(first - 80);
+    }
 
-    while nested.limit() > 0 {
-        buf.push(read_base128(&mut nested)?)
+    while !nested.is_empty() {
+        buf.push(read_subidentifier(&mut nested)?)
     }
 
     Ok(buf)
 }
 
+/// Read OBJECT IDENTIFIER subidentifier, rejecting padded ones and ones over 64 bits.
+fn read_subidentifier<R: IoRead>(r: &mut R) -> Result<u64, ReadError> {
+    let mut byte = read_byte(r)?;
+    if byte == 0x80 {
+        return Err(ReadError::InvalidValue);
+    }
+
+    let mut i: u64 = 0;
+    loop {
+        if i >> 57 != 0 {
+            return Err(ReadError::InvalidValue);
+        }
+        i = (i << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok(i);
+        }
+        byte = read_byte(r)?;
+    }
+}
+
 #[cfg(test)]
 #[allow(dead_code)]
 mod tests {
-    use std::io::ErrorKind as IoErrorKind;
+    use io::ErrorKind as IoErrorKind;
 
     use quickcheck::{Arbitrary, Gen};
 
@@ -385,6 +658,59 @@ mod tests {
                     |r, len| read_u64(r, len).unwrap())
     }
 
+    fn real_helper(v: f64) -> bool {
+        read_helper(&Tag::primitive(Class::Universal, 0x09),
+                    &v,
+                    |w, tag, v| write_real64(w, tag, *v).unwrap(),
+                    |r, len| read_f64(r, len).unwrap())
+    }
+
+    #[quickcheck]
+    fn real(v: f64) -> bool {
+        real_helper(v)
+    }
+
+    #[test]
+    fn real_special() {
+        let values = [0.0, -0.0, 1.0, -1.5, 0.1, 1e300, -2.5e-310, ::std::f64::MAX,
+                      ::std::f64::MIN_POSITIVE, ::std::f64::INFINITY, ::std::f64::NEG_INFINITY];
+        for v in values.iter() {
+            assert!(real_helper(*v), "{}", v);
+        }
+
+        let mut buf = Vec::new();
+        write_real64(&mut buf, &Tag::primitive(Class::Universal, 0x09), ::std::f64::NAN).unwrap();
+        assert!(read_f64(&mut &buf[2..], 1).unwrap().is_nan());
+    }
+
+    #[test]
+    fn real_huge_exponent() {
+        // base 16, 4 octets of exponent
+        let buf = [0xa3, 0x04, 0x7f, 0xff, 0xff, 0xff, 0x01];
+        assert_eq!(read_f64(&mut &buf[..], buf.len()).unwrap(), ::std::f64::INFINITY);
+        let buf = [0xa3, 0x04, 0x80, 0x00, 0x00, 0x00, 0x01];
+        assert_eq!(read_f64(&mut &buf[..], buf.len()).unwrap(), 0.0);
+        // smallest subnormal, 1 * 2^-1074
+        let buf = [0x81, 0xfb, 0xce, 0x01];
+        assert_eq!(read_f64(&mut &buf[..], buf.len()).unwrap(), 5e-324);
+    }
+
+    #[test]
+    fn real_decimal() {
+        assert_eq!(read_f64(&mut &b"\x03-12.5E+1"[..], 9).unwrap(), -125.0);
+        assert_eq!(read_f64(&mut &b"\x01 42"[..], 4).unwrap(), 42.0);
+        assert_eq!(read_f64(&mut &b"\x02 1,5"[..], 5).unwrap(), 1.5);
+        assert_eq!(read_f64(&mut &b"\x02+.5"[..], 4).unwrap(), 0.5);
+        assert_eq!(read_f64(&mut &b"\x031.e-2"[..], 6).unwrap(), 0.01);
+
+        for bad in [&b"\x01inf"[..], b"\x02NaN", b"\x03infinity", b"\x011e5", b"\x0112.5",
+                    b"\x021", b"\x02.", b"\x031.5", b"\x031.5E", b"\x0142 ", b"\x01--1",
+                    b"\x04 1", b"\x00 1"]
+            .iter() {
+            assert!(read_f64(&mut &bad[..], bad.len()).is_err(), "{:?}", bad);
+        }
+    }
+
     #[quickcheck]
     fn octet_string(buf: Vec<u8>) -> bool {
         read_helper(&Tag::primitive(Class::Universal, 0x04),
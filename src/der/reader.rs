@@ -1,16 +1,28 @@
-use std::{self, io};
-use std::io::Read;
+use lib::{fmt, str, Box, Cow, String, ToString, Vec};
+use lib::fmt::Write;
+use lib::marker::PhantomData;
+use io;
 
 use info::{self, Tag, Len};
-use de::{self, Asn1Visitor, Asn1Deserialize, Asn1Deserializer, Asn1Error};
+use de::{self, Asn1Visitor, Asn1Deserialize, Asn1DeserializeOwned, Asn1Deserializer, Asn1Error,
+         DecoderConfig};
+use universal::{Any, ObjectIdentifierRef};
 
 use super::read;
+use super::write;
+use super::source::Source;
+#[cfg(feature = "std")]
+use super::source::IoSource;
 
+#[deprecated(note = "not implemented by any source, use `Deserializer::position` instead")]
 pub trait ReadCountExt: io::Read {
     fn bytes_read(&self) -> usize;
 }
 
-
+/// Error of DER decoding.
+///
+/// Errors returned by `Deserializer::decode` and `from_asn1` are wrapped in `At` with the
+/// location of the failed value, match on `kind()` or `into_kind()` to get the error itself.
 #[derive(Debug)]
 pub enum DecodeError {
     InvalidType(&'static str),
@@ -20,8 +32,151 @@ pub enum DecodeError {
     LengthMismatch(Len, Len),
     InvalidValue(&'static str),
     ConstructedNotConsumed,
+    /// Input ended inside the value starting at the offset.
+    Truncated(usize),
+    /// Frame of the given length, or at least that long, exceeds the maximum size.
+    FrameTooLarge(usize),
+    /// Input is longer than `DecoderConfig::max_input_len`.
+    InputTooLong(usize),
+    /// Value content is longer than `DecoderConfig::max_element_len`.
+    ElementTooLong(usize),
+    /// Constructed values are nested deeper than `DecoderConfig::max_depth`.
+    NestingTooDeep(usize),
+    /// Constructed value has more components than `DecoderConfig::max_seq_len`.
+    TooManyElements(usize),
+    /// OBJECT IDENTIFIER has more arcs than `DecoderConfig::max_oid_arcs`.
+    TooManyArcs(usize),
     Custom(String),
-    IO(std::io::Error),
+    IO(io::Error),
+    /// Error in the value starting at byte `offset`. `path` leads to the value from the
+    /// top-level type through field names and element indices, e.g. `Certificate.tbs[3]`.
+    At {
+        offset: usize,
+        path: String,
+        error: Box<DecodeError>,
+    },
+}
+
+impl DecodeError {
+    /// Get the error without its location.
+    pub fn kind(&self) -> &DecodeError {
+        match *self {
+            DecodeError::At { ref error, .. } => error,
+            ref e => e,
+        }
+    }
+
+    /// Get the error without its location, by value.
+    pub fn into_kind(self) -> DecodeError {
+        match self {
+            DecodeError::At { error, .. } => *error,
+            e => e,
+        }
+    }
+
+    /// Offset of the value the error occurred in, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match *self {
+            DecodeError::At { offset, .. } => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Path to the value the error occurred in, if known.
+    pub fn path(&self) -> Option<&str> {
+        match *self {
+            DecodeError::At { ref path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Move the offset by `by`, for errors of values decoded from a part of the input.
+    pub fn shifted(self, by: usize) -> Self {
+        match self {
+            DecodeError::At { offset, path, error } => {
+                DecodeError::At {
+                    offset: offset + by,
+                    path: path,
+                    error: error,
+                }
+            }
+            e => e,
+        }
+    }
+
+    /// Prepend `segment` to the path. The offset is the one of the innermost value.
+    pub fn within(self, offset: usize, segment: &str) -> Self {
+        match self {
+            DecodeError::At { offset, mut path, error } => {
+                path.insert_str(0, segment);
+                DecodeError::At {
+                    offset: offset,
+                    path: path,
+                    error: error,
+                }
+            }
+            e => {
+                DecodeError::At {
+                    offset: offset,
+                    path: segment.to_string(),
+                    error: Box::new(e),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::InvalidType(msg) => write!(f, "invalid type: {}", msg),
+            DecodeError::InvalidTag(msg) => write!(f, "invalid tag: {}", msg),
+            DecodeError::TagMismatch(ref expected, ref got) => {
+                write!(f, "expected tag {:?}, found {:?}", expected, got)
+            }
+            DecodeError::InvalidLength(msg) => write!(f, "invalid length: {}", msg),
+            DecodeError::LengthMismatch(ref expected, ref got) => {
+                write!(f, "expected length {:?}, found {:?}", expected, got)
+            }
+            DecodeError::InvalidValue(msg) => write!(f, "invalid value: {}", msg),
+            DecodeError::ConstructedNotConsumed => {
+                f.write_str("constructed value has trailing data")
+            }
+            DecodeError::Truncated(offset) => {
+                write!(f, "input ended inside the value at offset {}", offset)
+            }
+            DecodeError::FrameTooLarge(len) => {
+                write!(f, "frame of {} bytes exceeds the maximum size", len)
+            }
+            DecodeError::InputTooLong(max) => write!(f, "input is longer than {} bytes", max),
+            DecodeError::ElementTooLong(max) => write!(f, "value is longer than {} bytes", max),
+            DecodeError::NestingTooDeep(max) => {
+                write!(f, "values are nested deeper than {} levels", max)
+            }
+            DecodeError::TooManyElements(max) => {
+                write!(f, "value has more than {} components", max)
+            }
+            DecodeError::TooManyArcs(max) => {
+                write!(f, "object identifier has more than {} arcs", max)
+            }
+            DecodeError::Custom(ref msg) => f.write_str(msg),
+            DecodeError::IO(ref e) => e.fmt(f),
+            DecodeError::At { offset, ref path, ref error } => {
+                write!(f, "{} in {} at offset {}", error, path, offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn (::std::error::Error) + 'static)> {
+        match *self {
+            DecodeError::IO(ref e) => Some(e),
+            DecodeError::At { ref error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
 }
 
 impl From<io::Error> for DecodeError {
@@ -30,6 +185,13 @@ impl From<io::Error> for DecodeError {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<::std::io::Error> for DecodeError {
+    fn from(e: ::std::io::Error) -> Self {
+        DecodeError::IO(e.into())
+    }
+}
+
 impl From<read::ReadError> for DecodeError {
     fn from(e: read::ReadError) -> Self {
         use self::read::ReadError;
@@ -44,7 +206,7 @@ impl From<read::ReadError> for DecodeError {
 
 impl Asn1Error for DecodeError {
     fn custom<T>(msg: T) -> Self
-        where T: std::fmt::Display
+        where T: fmt::Display
     {
 
         DecodeError::Custom(msg.to_string())
@@ -77,72 +239,255 @@ impl Asn1Error for DecodeError {
 
 
 #[derive(Debug)]
-pub struct Deserializer<R: io::Read> {
-    inner: R,
+pub struct Deserializer<S> {
+    inner: read::Limited<S>,
     peeked_tag: Option<Tag>,
+    /// Offset of the peeked tag.
+    peeked_at: usize,
     implicit_tag: Option<Tag>,
+    config: DecoderConfig,
+    depth: usize,
 }
 
+/// Components of a constructed value. They are decoded by the parent deserializer, so values
+/// of recursive types do not need a new reader type for every nesting level.
 #[derive(Debug)]
-struct SeqAccessor<R: io::Read> {
-    inner: io::Take<R>,
+struct SeqAccessor<'a, S: 'a> {
+    de: &'a mut Deserializer<S>,
+    count: usize,
 }
 
-impl<'de, R: io::Read + 'de> SeqAccessor<R> {
-    fn new(nested: io::Take<R>) -> Self {
-        SeqAccessor { inner: nested }
+impl<'de, 'a, S: Source<'de>> de::SeqAccess<'de> for SeqAccessor<'a, S> {
+    type Err = DecodeError;
+
+    fn next_field<V>(&mut self) -> Result<V, Self::Err>
+        where V: Asn1Deserialize<'de>
+    {
+        let index = self.count;
+        self.field(|segment| write!(segment, "[{}]", index))
     }
 
-    fn next(&mut self) -> Deserializer<&mut io::Take<R>> {
-        Deserializer::new(&mut self.inner)
+    fn next_named_field<V>(&mut self, name: &'static str) -> Result<V, Self::Err>
+        where V: Asn1Deserialize<'de>
+    {
+        self.field(|segment| write!(segment, ".{}", name))
     }
-}
 
-impl<'de, R: io::Read> de::SeqAccess<'de> for SeqAccessor<R> {
-    type Err = DecodeError;
+    fn remaining(&self) -> u64 {
+        self.de.inner.remaining().unwrap_or(0) as u64
+    }
+}
 
-    fn next_field<V>(&mut self) -> Result<V, Self::Err>
-        where V: Asn1Deserialize
+impl<'de, 'a, S: Source<'de>> SeqAccessor<'a, S> {
+    /// Deserialize next field, errors are located by the path segment written by `segment`.
+    fn field<V, F>(&mut self, segment: F) -> Result<V, DecodeError>
+        where V: Asn1Deserialize<'de>,
+              F: FnOnce(&mut String) -> fmt::Result
     {
-        V::asn1_deserialize(self.next())
+        if self.count == self.de.config.max_seq_len {
+            return Err(DecodeError::TooManyElements(self.de.config.max_seq_len));
+        }
+        self.count += 1;
+
+        let offset = self.de.value_start();
+        V::asn1_deserialize(&mut *self.de).map_err(|e| {
+            let mut path = String::new();
+            let _ = segment(&mut path);
+            e.within(offset, &path)
+        })
     }
+}
 
-    fn remaining(&self) -> u64 {
-        self.inner.limit()
+/// Elements of a SEQUENCE OF decoded one at a time, see `Deserializer::seq_of`. Iteration
+/// stops after the first error.
+///
+/// Dropping the iterator before the end skips the rest of the SEQUENCE OF unchecked, use
+/// `finish` to check the framing of the remaining elements.
+#[derive(Debug)]
+pub struct SeqOfItems<'a, S: 'a + io::Read, T> {
+    de: &'a mut Deserializer<S>,
+    /// Input limit outside of the SEQUENCE OF.
+    outer: Option<usize>,
+    start: usize,
+    count: usize,
+    done: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<'de, 'a, S: Source<'de>, T> SeqOfItems<'a, S, T> {
+    /// Number of elements decoded so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Skip the remaining elements without decoding them.
+    pub fn finish(mut self) -> Result<(), DecodeError> {
+        while self.de.inner.remaining().unwrap_or(0) > 0 {
+            let index = self.count;
+            let offset = self.de.value_start();
+            self.count += 1;
+            if let Err(e) = self.de.skip_value() {
+                return Err(self.locate(e, index, offset));
+            }
+        }
+        Ok(())
+    }
+
+    /// Locate error `e` in element `index` starting at `offset`.
+    fn locate(&self, e: DecodeError, index: usize, offset: usize) -> DecodeError {
+        let mut segment = String::new();
+        let _ = write!(segment, "[{}]", index);
+        e.within(offset, &segment).within(self.start, info::TYPE_SEQUENCE_OF)
+    }
+}
+
+impl<'de, 'a, S: Source<'de>, T: Asn1Deserialize<'de>> Iterator for SeqOfItems<'a, S, T> {
+    type Item = Result<T, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.de.inner.remaining().unwrap_or(0) == 0 {
+            self.done = true;
+            return None;
+        }
+
+        let index = self.count;
+        let offset = self.de.value_start();
+        let result = if index == self.de.config.max_seq_len {
+            Err(DecodeError::TooManyElements(self.de.config.max_seq_len))
+        } else {
+            self.count += 1;
+            T::asn1_deserialize(&mut *self.de)
+        };
+
+        match result {
+            Ok(value) => Some(Ok(value)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(self.locate(e, index, offset)))
+            }
+        }
+    }
+}
+
+impl<'a, S: 'a + io::Read, T> Drop for SeqOfItems<'a, S, T> {
+    fn drop(&mut self) {
+        // errors of the input show up again when decoding the next value
+        if let Some(remaining) = self.de.inner.remaining() {
+            self.de.peeked_tag = None;
+            self.de.implicit_tag = None;
+            let _ = read::skip(&mut self.de.inner, remaining);
+        }
+        self.de.depth -= 1;
+        self.de.inner.set_limit(self.outer);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: ::std::io::Read> Deserializer<IoSource<R>> {
+    /// Create deserializer reading from `reader`, decoded values never borrow from it.
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::new(IoSource::new(reader))
     }
 }
 
-impl<'de, R: io::Read> Deserializer<R> {
-    pub fn new(reader: R) -> Self {
+impl<'de, S: Source<'de>> Deserializer<S> {
+    /// Create deserializer reading from `source`. Slice sources let values borrow from the input.
+    pub fn new(source: S) -> Self {
         Deserializer {
-            inner: reader,
+            inner: read::Limited::new(source),
             peeked_tag: None,
+            peeked_at: 0,
             implicit_tag: None,
+            config: DecoderConfig::default(),
+            depth: 0,
         }
     }
 
-    pub fn with_tag(self, tag: Tag) -> Self {
-        Deserializer {
-            inner: self.inner,
-            peeked_tag: self.peeked_tag,
-            implicit_tag: Some(tag),
+    /// Set limits enforced on the input.
+    pub fn with_config(mut self, config: DecoderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_tag(mut self, tag: Tag) -> Self {
+        self.implicit_tag = Some(tag);
+        self
+    }
+
+    /// Start at nesting depth `depth`, for a value decoded apart from the input it is nested in.
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.inner.position()
+    }
+
+    /// Offset of the next value in the input.
+    fn value_start(&self) -> usize {
+        if self.peeked_tag.is_some() {
+            self.peeked_at
+        } else {
+            self.inner.position()
+        }
+    }
+
+    /// Decode top-level value of type `T`. Errors are located by the offset and path of the
+    /// value they occurred in.
+    pub fn decode<T: Asn1Deserialize<'de>>(&mut self) -> Result<T, DecodeError> {
+        let offset = self.value_start();
+        T::asn1_deserialize(&mut *self).map_err(|e| e.within(offset, T::asn1_type()))
+    }
+
+    /// Start decoding a SEQUENCE OF whose elements are then decoded one at a time by the
+    /// returned iterator, so huge lists are processed in constant memory.
+    pub fn seq_of<T: Asn1Deserialize<'de>>(&mut self) -> Result<SeqOfItems<S, T>, DecodeError> {
+        let start = self.value_start();
+        let outer = self.override_tag(info::TAG_SEQUENCE, |d, expected_tag| {
+                let tag = d.read_tag()?;
+                let len = d.read_length_def()?;
+
+                if tag == expected_tag {
+                    d.enter_constructed(len)
+                } else {
+                    Err(DecodeError::TagMismatch(expected_tag, tag))
+                }
+            })
+            .map_err(|e| e.within(start, info::TYPE_SEQUENCE_OF))?;
+
+        Ok(SeqOfItems {
+               de: self,
+               outer: outer,
+               start: start,
+               count: 0,
+               done: false,
+               _marker: PhantomData,
+           })
+    }
+
+    /// Check if the input ended right before the next value.
+    pub fn at_end(&mut self) -> Result<bool, DecodeError> {
+        let start = self.inner.position();
+        match self.peek_tag() {
+            Ok(_) => Ok(false),
+            Err(DecodeError::IO(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof &&
+                                           self.inner.position() == start => Ok(true),
+            Err(e) => Err(e),
         }
     }
 
     pub fn override_tag<T, F>(&mut self, default_tag: Tag, f: F) -> Result<T, DecodeError>
         where F: FnOnce(&mut Self, Tag) -> Result<T, DecodeError>
     {
-        let tag = if let Some(tag) = self.implicit_tag {
-            tag
-        } else {
-            default_tag
-        };
+        let tag = self.implicit_tag.take().unwrap_or(default_tag);
         f(self, tag)
     }
 
     #[inline]
     pub fn decode_primitive<T, F>(&mut self, expected_tag: Tag, f: F) -> Result<T, DecodeError>
-        where F: FnOnce(&mut R, usize) -> Result<T, DecodeError>
+        where F: FnOnce(&mut read::Limited<S>, usize) -> Result<T, DecodeError>
     {
         let tag = self.read_tag()?;
         let len = self.read_length_def()?;
@@ -154,10 +499,51 @@ impl<'de, R: io::Read> Deserializer<R> {
         }
     }
 
+    /// Decode contents of a constructed value of length `len` and check they were consumed.
+    fn decode_constructed<T, F>(&mut self, len: usize, f: F) -> Result<T, DecodeError>
+        where F: FnOnce(SeqAccessor<S>) -> Result<T, DecodeError>
+    {
+        let end = self.inner.position() + len;
+        let outer = self.enter_constructed(len)?;
+        let result = f(SeqAccessor {
+                           de: self,
+                           count: 0,
+                       });
+        self.leave_constructed(outer);
+
+        let value = result?;
+        if self.inner.position() == end {
+            Ok(value)
+        } else {
+            Err(DecodeError::ConstructedNotConsumed)
+        }
+    }
+
+    /// Limit the input to contents of length `len`, returning the previous limit.
+    fn enter_constructed(&mut self, len: usize) -> Result<Option<usize>, DecodeError> {
+        if self.inner.remaining().map_or(false, |remaining| len > remaining) {
+            return Err(DecodeError::InvalidLength("value exceeds enclosing value"));
+        }
+
+        if self.depth == self.config.max_depth {
+            return Err(DecodeError::NestingTooDeep(self.config.max_depth));
+        }
+
+        let end = self.inner.position() + len;
+        self.depth += 1;
+        Ok(self.inner.set_limit(Some(end)))
+    }
+
+    fn leave_constructed(&mut self, outer: Option<usize>) {
+        self.depth -= 1;
+        self.inner.set_limit(outer);
+    }
+
     fn peek_tag(&mut self) -> Result<Tag, DecodeError> {
         if let Some(tag) = self.peeked_tag {
             Ok(tag)
         } else {
+            self.peeked_at = self.inner.position();
             let tag = read::read_tag(&mut self.inner)?;
             self.peeked_tag = Some(tag);
             Ok(tag)
@@ -165,7 +551,7 @@ impl<'de, R: io::Read> Deserializer<R> {
     }
 
     fn read_tag(&mut self) -> Result<Tag, DecodeError> {
-        if let Some(tag) = self.peeked_tag {
+        if let Some(tag) = self.peeked_tag.take() {
             Ok(tag)
         } else {
             let tag = read::read_tag(&mut self.inner)?;
@@ -173,18 +559,109 @@ impl<'de, R: io::Read> Deserializer<R> {
         }
     }
 
+    /// Skip next value with all of its contents.
+    fn skip_value(&mut self) -> Result<(), DecodeError> {
+        self.implicit_tag = None;
+        self.read_tag()?;
+        let len = self.read_length_def()?;
+        self.inner.skip_bytes(len)?;
+        Ok(())
+    }
+
     fn read_length_def(&mut self) -> Result<usize, DecodeError> {
-        read::read_len_def(&mut self.inner).map_err(|e| e.into())
+        let len = read::read_len_def(&mut self.inner)?;
+        self.check_len(len)?;
+        Ok(len)
+    }
+
+    /// Check content length `len` just read against the limits.
+    fn check_len(&self, len: usize) -> Result<(), DecodeError> {
+        if len > self.config.max_element_len {
+            Err(DecodeError::ElementTooLong(self.config.max_element_len))
+        } else if self.inner.position().saturating_add(len) > self.config.max_input_len {
+            Err(DecodeError::InputTooLong(self.config.max_input_len))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Check OBJECT IDENTIFIER content `bytes` has at most `max_arcs` arcs.
+fn check_arcs<'a>(bytes: Cow<'a, [u8]>, max_arcs: usize) -> Result<Cow<'a, [u8]>, DecodeError> {
+    // the last octet of every subidentifier has the high bit clear, the first one holds two arcs
+    let arcs = bytes.iter().filter(|&&b| b & 0x80 == 0).count() + 1;
+    if arcs > max_arcs {
+        Err(DecodeError::TooManyArcs(max_arcs))
+    } else {
+        Ok(bytes)
+    }
+}
+
+macro_rules! forward_to_ref {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+                where V: Asn1Visitor<'de>
+            {
+                (&mut self).$method(visitor)
+            }
+        )*
+    }
+}
+
+impl<'de, S: Source<'de>> Asn1Deserializer<'de> for Deserializer<S> {
+    type Err = DecodeError;
+
+    type ExplicitDeserializer = Self;
+    type ImplicitDeserializer = Self;
+
+    forward_to_ref! {
+        deserialize_bool
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64
+        deserialize_bit_string deserialize_bytes deserialize_null deserialize_object_identifier
+        deserialize_raw deserialize_seq deserialize_choice deserialize_any
+        deserialize_ignored_any
+    }
+
+    fn deserialize_tagged(self, tag: Tag) -> Result<Self::ExplicitDeserializer, Self::Err> {
+        // This is DER decoder, so all tagging MUST be implicit
+        self.deserialize_tagged_implicit(tag)
+    }
+
+    fn deserialize_tagged_implicit(self,
+                                   tag: Tag)
+                                   -> Result<Self::ImplicitDeserializer, Self::Err> {
+        Ok(self.with_tag(tag))
+    }
+
+    fn decoder_config(&self) -> Option<DecoderConfig> {
+        Some(self.config)
+    }
+
+    fn value_depth(&self) -> Option<usize> {
+        Some(self.depth)
+    }
+
+    fn value_offset(&self) -> Option<usize> {
+        Some(self.value_start())
+    }
+
+    fn deserialize_captured<T>(mut self) -> Result<(T, Vec<u8>), Self::Err>
+        where T: Asn1DeserializeOwned
+    {
+        (&mut self).deserialize_captured()
     }
 }
 
-impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
+impl<'de, 'a, S: Source<'de>> Asn1Deserializer<'de> for &'a mut Deserializer<S> {
     type Err = DecodeError;
 
     type ExplicitDeserializer = Self;
     type ImplicitDeserializer = Self;
 
-    fn deserialize_bool<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_BOOLEAN, |d, tag| {
@@ -194,7 +671,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
         })
     }
 
-    fn deserialize_i8<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -202,7 +679,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
                 .and_then(|v| visitor.visit_i8(v))
         })
     }
-    fn deserialize_i16<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -210,7 +687,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
                 .and_then(|v| visitor.visit_i16(v))
         })
     }
-    fn deserialize_i32<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -218,7 +695,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
                 .and_then(|v| visitor.visit_i32(v))
         })
     }
-    fn deserialize_i64<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -227,7 +704,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
         })
     }
 
-    fn deserialize_u8<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -235,7 +712,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
                 .and_then(|v| visitor.visit_u8(v))
         })
     }
-    fn deserialize_u16<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -243,7 +720,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
                 .and_then(|v| visitor.visit_u16(v))
         })
     }
-    fn deserialize_u32<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -251,7 +728,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
                 .and_then(|v| visitor.visit_u32(v))
         })
     }
-    fn deserialize_u64<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_INTEGER, |d, tag| {
@@ -260,7 +737,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
         })
     }
 
-    fn deserialize_f32<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_REAL, |d, tag| {
@@ -268,7 +745,7 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
                 .and_then(|v| visitor.visit_f32(v))
         })
     }
-    fn deserialize_f64<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_REAL, |d, tag| {
@@ -277,25 +754,30 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
         })
     }
 
-    fn deserialize_bit_string<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_bit_string<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_BIT_STRING, |d, tag| {
-            d.decode_primitive(tag,
-                               |r, len| read::read_bit_string(r, len).map_err(|e| e.into()))
-                .and_then(|v| visitor.visit_bit_string(v))
+            d.decode_primitive(tag, |r, len| {
+                let unused = read::read_unused_bits(r, len)?;
+                match r.read_bytes(len - 1)? {
+                    Cow::Borrowed(v) => visitor.visit_borrowed_bit_string(unused, v),
+                    Cow::Owned(v) => visitor.visit_bit_string((unused, v)),
+                }
+            })
         })
     }
-    fn deserialize_bytes<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_OCTET_STRING, |d, tag| {
-            d.decode_primitive(tag,
-                               |r, len| read::read_octet_string(r, len).map_err(|e| e.into()))
-                .and_then(|v| visitor.visit_byte_string(v))
+            d.decode_primitive(tag, |r, len| match r.read_bytes(len)? {
+                Cow::Borrowed(v) => visitor.visit_borrowed_bytes(v),
+                Cow::Owned(v) => visitor.visit_byte_string(v),
+            })
         })
     }
-    fn deserialize_null<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_null<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_NULL, |d, tag| {
@@ -307,16 +789,53 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
         })
     }
 
-    fn deserialize_object_identifier<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_object_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
+        let max_arcs = self.config.max_oid_arcs;
         self.override_tag(info::TAG_OBJECT_IDENTIFIER, |d, tag| {
-            d.decode_primitive(tag,
-                               |r, len| read::read_object_identifier(r, len).map_err(|e| e.into()))
-                .and_then(|v| visitor.visit_object_identifier(v))
+            d.decode_primitive(tag, |r, len| match check_arcs(r.read_bytes(len)?, max_arcs)? {
+                Cow::Borrowed(v) => {
+                    if ObjectIdentifierRef::from_bytes(v).is_none() {
+                        return Err(DecodeError::InvalidValue("bad object identifier encoding"));
+                    }
+                    visitor.visit_borrowed_object_identifier(v)
+                }
+                Cow::Owned(v) => {
+                    let oid = read::read_object_identifier(&mut v.as_slice(), len)?;
+                    visitor.visit_object_identifier(oid)
+                }
+            })
         })
     }
 
+    fn deserialize_raw<V>(self, visitor: V) -> Result<V::Value, Self::Err>
+        where V: Asn1Visitor<'de>
+    {
+        let mut raw: Vec<u8> = Vec::new();
+
+        let tag = if let Some(tag) = self.peeked_tag.take() {
+            write::write_tag(&mut raw, &tag)?;
+            tag
+        } else {
+            read::read_tag(&mut read::Recorder::new(&mut self.inner, &mut raw))?
+        };
+
+        if let Some(expected_tag) = self.implicit_tag.take() {
+            if tag != expected_tag {
+                return Err(DecodeError::TagMismatch(expected_tag, tag));
+            }
+        }
+
+        let len = read::read_len_def(&mut read::Recorder::new(&mut self.inner, &mut raw))?;
+        self.check_len(len)?;
+        let header_len = raw.len();
+
+        read::read_into(&mut self.inner, len, &mut raw)?;
+
+        visitor.visit_raw(tag, header_len, raw)
+    }
+
     fn deserialize_tagged(self, tag: Tag) -> Result<Self::ExplicitDeserializer, Self::Err> {
         // This is DER decoder, so all tagging MUST be implicit
         self.deserialize_tagged_implicit(tag)
@@ -325,10 +844,11 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
     fn deserialize_tagged_implicit(self,
                                    tag: Tag)
                                    -> Result<Self::ImplicitDeserializer, Self::Err> {
-        Ok(self.with_tag(tag))
+        self.implicit_tag = Some(tag);
+        Ok(self)
     }
 
-    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         self.override_tag(info::TAG_SEQUENCE, |d, expected_tag| {
@@ -336,18 +856,169 @@ impl<'de, R: io::Read + 'de> Asn1Deserializer<'de> for Deserializer<R> {
             let len = d.read_length_def()?;
 
             if tag == expected_tag {
-                visitor.visit_seq(SeqAccessor::new(d.inner.by_ref().take(len as u64)))
+                d.decode_constructed(len, |seq| visitor.visit_seq(seq))
             } else {
                 Err(DecodeError::TagMismatch(expected_tag, tag))
             }
         })
     }
 
-    fn deserialize_choice<V>(mut self, visitor: V) -> Result<V::Value, Self::Err>
+    fn deserialize_choice<V>(self, visitor: V) -> Result<V::Value, Self::Err>
         where V: Asn1Visitor<'de>
     {
         let tag = self.peek_tag()?;
-        visitor.visit_choice(&tag, self.with_tag(tag))
+        self.implicit_tag = Some(tag);
+        visitor.visit_choice(&tag, self)
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Err>
+        where V: Asn1Visitor<'de>
+    {
+        // the peeked tag selects the type, so universal types use their own tag
+        let tag = self.peek_tag()?;
+        self.implicit_tag = None;
+        let d = self;
+
+        match tag {
+            info::TAG_BOOLEAN => d.deserialize_bool(visitor),
+            info::TAG_INTEGER => {
+                d.decode_primitive(tag, |r, len| if len <= 8 {
+                    let v = read::read_i64(r, len)?;
+                    visitor.visit_i64(v)
+                } else {
+                    // does not fit, leave it to the visitor
+                    let mut raw = Vec::new();
+                    write::_write_header(&mut raw, &tag, len)?;
+                    let header_len = raw.len();
+                    read::read_into(r, len, &mut raw)?;
+                    visitor.visit_raw(tag, header_len, raw)
+                })
+            }
+            info::TAG_BIT_STRING => d.deserialize_bit_string(visitor),
+            info::TAG_OCTET_STRING => d.deserialize_bytes(visitor),
+            info::TAG_NULL => d.deserialize_null(visitor),
+            info::TAG_OBJECT_IDENTIFIER => d.deserialize_object_identifier(visitor),
+            info::TAG_REAL => d.deserialize_f64(visitor),
+            info::TAG_UTF8_STRING |
+            info::TAG_NUMERIC_STRING |
+            info::TAG_PRINTABLE_STRING |
+            info::TAG_IA5_STRING |
+            info::TAG_UTC_TIME |
+            info::TAG_GENERALIZED_TIME |
+            info::TAG_VISIBLE_STRING => {
+                d.decode_primitive(tag, |r, len| match r.read_bytes(len)? {
+                    Cow::Borrowed(v) => {
+                        let s = str::from_utf8(v)
+                            .map_err(|_| DecodeError::InvalidValue("bad character string"))?;
+                        visitor.visit_borrowed_str(tag, s)
+                    }
+                    Cow::Owned(v) => {
+                        let s = String::from_utf8(v)
+                            .map_err(|_| DecodeError::InvalidValue("bad character string"))?;
+                        visitor.visit_string(tag, s)
+                    }
+                })
+            }
+            info::TAG_SEQUENCE => d.deserialize_seq(visitor),
+            info::TAG_SET => {
+                d.read_tag()?;
+                let len = d.read_length_def()?;
+                d.decode_constructed(len, |set| visitor.visit_set(set))
+            }
+            _ if tag.class != info::Universal && tag.is_constructed() => {
+                d.read_tag()?;
+                let len = d.read_length_def()?;
+                d.decode_constructed(len, |contents| visitor.visit_tagged(tag, contents))
+            }
+            _ => d.deserialize_raw(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Err>
+        where V: Asn1Visitor<'de>
+    {
+        self.skip_value()?;
+        visitor.visit_ignored()
+    }
+
+    fn decoder_config(&self) -> Option<DecoderConfig> {
+        Some(self.config)
+    }
+
+    fn value_depth(&self) -> Option<usize> {
+        Some(self.depth)
+    }
+
+    fn value_offset(&self) -> Option<usize> {
+        Some(self.value_start())
+    }
+
+    fn deserialize_captured<T>(self) -> Result<(T, Vec<u8>), Self::Err>
+        where T: Asn1DeserializeOwned
+    {
+        // the value is decoded from its encoding as if it was still in the input
+        let start = self.value_start();
+        let implicit_tag = self.implicit_tag;
+        let raw = Any::asn1_deserialize(&mut *self)?.into_inner();
+        let value = {
+            let mut de = Deserializer::new(raw.as_slice())
+                .with_config(self.config)
+                .with_depth(self.depth);
+            de.implicit_tag = implicit_tag;
+            T::asn1_deserialize(&mut de).map_err(|e| e.shifted(start))?
+        };
+        Ok((value, raw))
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::Deserializer;
+    use universal::OctetString;
+
+    #[test]
+    fn seq_of_items() {
+        let values: Vec<OctetString> = (0..3).map(|i| OctetString::new(vec![i; 200])).collect();
+        let mut buf = ::to_asn1(&values).unwrap();
+        buf.extend(::to_asn1(&7u32).unwrap());
+
+        let mut de = Deserializer::from_reader(Cursor::new(&buf));
+        let out: Result<Vec<OctetString>, _> = de.seq_of().unwrap().collect();
+        assert_eq!(out.unwrap(), values);
+        assert_eq!(de.decode::<u32>().unwrap(), 7);
+
+        // remaining elements are skipped
+        let mut de = Deserializer::new(buf.as_slice());
+        {
+            let mut items = de.seq_of::<OctetString>().unwrap();
+            assert_eq!(items.next().unwrap().unwrap(), values[0]);
+            items.finish().unwrap();
+        }
+        assert_eq!(de.decode::<u32>().unwrap(), 7);
+
+        // also when the iterator is dropped early
+        let mut de = Deserializer::from_reader(Cursor::new(&buf));
+        assert_eq!(de.seq_of::<OctetString>().unwrap().next().unwrap().unwrap(), values[0]);
+        assert_eq!(de.decode::<u32>().unwrap(), 7);
+        assert!(de.at_end().unwrap());
+    }
+
+    #[test]
+    fn seq_of_items_error() {
+        let buf = ::to_asn1(&vec![1u32, 300, 2]).unwrap();
+        let mut de = Deserializer::new(buf.as_slice());
+        let mut items = de.seq_of::<u8>().unwrap();
+        assert_eq!(items.next().unwrap().unwrap(), 1);
+        let e = items.next().unwrap().unwrap_err();
+        assert_eq!(e.path(), Some("SEQUENCE OF[1]"));
+        assert_eq!(e.offset(), Some(5));
+        assert!(items.next().is_none());
+
+        let mut de = Deserializer::new(&buf[..4]);
+        assert!(de.seq_of::<u8>().unwrap().next().unwrap().is_err());
+        let buf = ::to_asn1(&1u32).unwrap();
+        assert!(Deserializer::new(buf.as_slice()).seq_of::<u8>().is_err());
+    }
+}
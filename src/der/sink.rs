@@ -0,0 +1,395 @@
+use lib::Vec;
+use io;
+
+/// Output of the DER serializer.
+///
+/// Constructed values are written in place: `open` reserves one length octet in front of the
+/// content, which `LengthPatcher` fills in once the content is written.
+pub trait Sink: io::Write {
+    /// Start content of a constructed value, returns its mark.
+    fn open(&mut self) -> io::Result<usize>;
+
+    /// Number of bytes written.
+    fn position(&self) -> usize;
+
+    /// Get the bytes written from `from` on, after appending `extra` zero bytes.
+    fn grow(&mut self, from: usize, extra: usize) -> io::Result<&mut [u8]>;
+
+    /// Finish constructed value started at `mark`, its length is already in place.
+    fn close(&mut self, mark: usize) -> io::Result<()> {
+        let _ = mark;
+        Ok(())
+    }
+
+    /// Check if long form lengths can be inserted when the outermost value is finished. Sinks
+    /// which can not keep track of them in memory get the content moved right away.
+    fn defer_lengths(&self) -> bool {
+        true
+    }
+}
+
+/// Number of length octets following the first one for content of length `len`.
+#[inline]
+fn extra_len_octets(len: usize) -> usize {
+    if len < 0x80 {
+        return 0;
+    }
+
+    let mut size = 1;
+    while size < 8 && (len as u64) >> (8 * size) != 0 {
+        size += 1;
+    }
+    size
+}
+
+/// Write long form length octets of `len` into `out`.
+#[inline]
+fn write_len_octets(out: &mut [u8], len: usize) {
+    let size = out.len();
+    for (i, octet) in out.iter_mut().enumerate() {
+        *octet = (len >> (8 * (size - 1 - i))) as u8;
+    }
+}
+
+/// Long form length octets to be inserted after the octet at `mark`.
+#[derive(Debug, Clone, Copy)]
+struct Gap {
+    mark: usize,
+    len: usize,
+    size: usize,
+    /// Sum of the sizes of this and all gaps recorded before.
+    total: usize,
+}
+
+/// Patcher of the lengths of constructed values written to a sink.
+///
+/// Short lengths are put into the reserved octet right away. Long form lengths are inserted
+/// when the outermost value is finished, in one pass moving every byte at most once, so deep
+/// structures are not copied once per level.
+#[derive(Debug, Default)]
+pub struct LengthPatcher {
+    /// Deferred lengths in the order the values were finished.
+    gaps: Vec<Gap>,
+    depth: usize,
+}
+
+impl LengthPatcher {
+    pub fn new() -> Self {
+        LengthPatcher::default()
+    }
+
+    /// Start content of a constructed value, returns its mark.
+    pub fn open<S: Sink + ?Sized>(&mut self, sink: &mut S) -> io::Result<usize> {
+        let mark = sink.open()?;
+        self.depth += 1;
+        Ok(mark)
+    }
+
+    /// Finish constructed value started at `mark`, putting its length in front of the content.
+    pub fn close<S: Sink + ?Sized>(&mut self, sink: &mut S, mark: usize) -> io::Result<()> {
+        self.depth -= 1;
+
+        // gaps of values finished since this one was opened lie inside of it
+        let mut len = sink.position() - mark - 1;
+        let total = match self.gaps.last() {
+            Some(gap) if gap.mark > mark => {
+                let first = self.gaps.partition_point(|gap| gap.mark < mark);
+                let outer = if first == 0 { 0 } else { self.gaps[first - 1].total };
+                len += gap.total - outer;
+                gap.total
+            }
+            Some(gap) => gap.total,
+            None => 0,
+        };
+
+        let size = extra_len_octets(len);
+        if size == 0 {
+            sink.grow(mark, 0)?[0] = len as u8;
+        } else if sink.defer_lengths() {
+            sink.grow(mark, 0)?[0] = 0x80 | size as u8;
+            self.gaps.push(Gap {
+                               mark: mark,
+                               len: len,
+                               size: size,
+                               total: total + size,
+                           });
+        } else {
+            let buf = sink.grow(mark, size)?;
+            buf[1..].rotate_right(size);
+            buf[0] = 0x80 | size as u8;
+            write_len_octets(&mut buf[1..1 + size], len);
+        }
+
+        if self.depth == 0 && !self.gaps.is_empty() {
+            self.insert_gaps(sink)?;
+        }
+        sink.close(mark)
+    }
+
+    /// Insert the deferred long form lengths, moving the content from the end backwards.
+    fn insert_gaps<S: Sink + ?Sized>(&mut self, sink: &mut S) -> io::Result<()> {
+        self.gaps.sort_unstable_by_key(|gap| gap.mark);
+        let from = self.gaps[0].mark;
+        let mut end = sink.position() - from;
+        let mut shift = self.gaps.iter().map(|gap| gap.size).sum();
+        let buf = sink.grow(from, shift)?;
+
+        for gap in self.gaps.iter().rev() {
+            let start = gap.mark - from + 1;
+            buf.copy_within(start..end, start + shift);
+            shift -= gap.size;
+            write_len_octets(&mut buf[start + shift..start + shift + gap.size], gap.len);
+            end = start;
+        }
+        self.gaps.clear();
+        Ok(())
+    }
+}
+
+impl Sink for Vec<u8> {
+    #[inline]
+    fn open(&mut self) -> io::Result<usize> {
+        self.push(0);
+        Ok(self.len() - 1)
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn grow(&mut self, from: usize, extra: usize) -> io::Result<&mut [u8]> {
+        if extra != 0 {
+            let len = self.len();
+            self.resize(len + extra, 0);
+        }
+        Ok(&mut self[from..])
+    }
+}
+
+impl<'a, S: Sink + ?Sized> Sink for &'a mut S {
+    #[inline]
+    fn open(&mut self) -> io::Result<usize> {
+        (**self).open()
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        (**self).position()
+    }
+
+    #[inline]
+    fn grow(&mut self, from: usize, extra: usize) -> io::Result<&mut [u8]> {
+        (**self).grow(from, extra)
+    }
+
+    #[inline]
+    fn close(&mut self, mark: usize) -> io::Result<()> {
+        (**self).close(mark)
+    }
+
+    #[inline]
+    fn defer_lengths(&self) -> bool {
+        (**self).defer_lengths()
+    }
+}
+
+/// Sink writing to `std::io::Write`. Constructed values are assembled in a single buffer, which
+/// is written out when the outermost one is finished.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoSink<W> {
+    inner: W,
+    buf: Vec<u8>,
+    depth: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: ::std::io::Write> IoSink<W> {
+    pub fn new(inner: W) -> Self {
+        IoSink {
+            inner: inner,
+            buf: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: ::std::io::Write> io::Write for IoSink<W> {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.depth == 0 {
+            self.inner.write_all(buf).map_err(io::Error::from)
+        } else {
+            self.buf.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: ::std::io::Write> Sink for IoSink<W> {
+    fn open(&mut self) -> io::Result<usize> {
+        self.depth += 1;
+        self.buf.open()
+    }
+
+    fn position(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn grow(&mut self, from: usize, extra: usize) -> io::Result<&mut [u8]> {
+        self.buf.grow(from, extra)
+    }
+
+    fn close(&mut self, _mark: usize) -> io::Result<()> {
+        self.depth -= 1;
+        if self.depth == 0 {
+            self.inner.write_all(&self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Sink writing into a fixed buffer, never allocates. Long form lengths are inserted right
+/// away, as there is no memory to keep track of them.
+#[derive(Debug)]
+pub struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        SliceSink { buf: buf, pos: 0 }
+    }
+
+    /// Number of bytes written.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn overflow() -> io::Error {
+        io::Error::new(io::ErrorKind::WriteZero)
+    }
+}
+
+impl<'a> io::Write for SliceSink<'a> {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let end = self.pos + buf.len();
+        if end > self.buf.len() {
+            return Err(SliceSink::overflow());
+        }
+        self.buf[self.pos..end].copy_from_slice(buf);
+        self.pos = end;
+        Ok(())
+    }
+}
+
+impl<'a> Sink for SliceSink<'a> {
+    #[inline]
+    fn open(&mut self) -> io::Result<usize> {
+        io::Write::write_all(self, &[0])?;
+        Ok(self.pos - 1)
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    fn grow(&mut self, from: usize, extra: usize) -> io::Result<&mut [u8]> {
+        if self.pos + extra > self.buf.len() {
+            return Err(SliceSink::overflow());
+        }
+        self.pos += extra;
+        Ok(&mut self.buf[from..self.pos])
+    }
+
+    #[inline]
+    fn defer_lengths(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use der;
+    use ser::Asn1Serialize;
+    use universal::{ObjectIdentifier, OctetString};
+
+    fn to_writer<T: Asn1Serialize>(value: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        value.asn1_serialize(der::Serializer::from_writer(&mut buf)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn long_length_patched() {
+        let v: Vec<u8> = (0..300).map(|x| x as u8).collect();
+        let buf = ::to_asn1(&vec![v.clone()]).unwrap();
+        assert_eq!(&buf[..8], &[0x30, 0x82, 0x04, 0x08, 0x30, 0x82, 0x04, 0x04]);
+        assert_eq!(::from_asn1::<Vec<Vec<u8>>>(&buf).unwrap(), vec![v]);
+    }
+
+    #[test]
+    fn nested_long_lengths() {
+        let inner = vec![vec![OctetString::new(vec![0xaa; 70000])], vec![]];
+        let v = vec![inner.clone(), inner.clone(), inner];
+        let buf = ::to_asn1(&v).unwrap();
+        assert_eq!(&buf[..10], &[0x30, 0x83, 0x03, 0x34, 0x83, 0x30, 0x83, 0x01, 0x11, 0x7c]);
+        assert_eq!(&buf[10..20], &[0x30, 0x83, 0x01, 0x11, 0x75, 0x04, 0x83, 0x01, 0x11, 0x70]);
+        assert_eq!(::from_asn1::<Vec<Vec<Vec<OctetString>>>>(&buf).unwrap(), v);
+        assert_eq!(to_writer(&v), buf);
+
+        let mut out = vec![0u8; buf.len()];
+        assert_eq!(::to_slice(&v, &mut out).unwrap(), buf.len());
+        assert_eq!(out, buf);
+    }
+
+    #[test]
+    fn io_sink() {
+        let v = vec![vec![OctetString::new(vec![0xaa; 200])], vec![]];
+        assert_eq!(to_writer(&v), ::to_asn1(&v).unwrap());
+        assert_eq!(to_writer(&5u8), &[0x02, 0x01, 0x05]);
+    }
+
+    #[test]
+    fn slice_sink() {
+        let v = vec![vec![OctetString::new(vec![0xaa; 200])], vec![]];
+        let expected = ::to_asn1(&v).unwrap();
+
+        let mut buf = [0u8; 256];
+        assert_eq!(::to_slice(&v, &mut buf).unwrap(), expected.len());
+        assert_eq!(&buf[..expected.len()], expected.as_slice());
+
+        match ::to_slice(&v, &mut buf[..expected.len() - 1]) {
+            Err(der::EncodeError::BufferTooSmall { needed }) => assert_eq!(needed, expected.len()),
+            other => panic!("unexpected {:?}", other),
+        }
+        // no room for the long form of the outer length
+        match ::to_slice(&v, &mut buf[..208]) {
+            Err(der::EncodeError::BufferTooSmall { needed }) => assert_eq!(needed, expected.len()),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // OBJECT IDENTIFIER too long for the stack buffer of short ones
+        let mut arcs = vec![1, 3];
+        arcs.extend((0..200).map(|i| i * 1000));
+        let oid = ObjectIdentifier::new(arcs);
+        let expected = ::to_asn1(&oid).unwrap();
+        let mut buf = [0u8; 1024];
+        assert_eq!(::to_slice(&oid, &mut buf).unwrap(), expected.len());
+        assert_eq!(&buf[..expected.len()], expected.as_slice());
+        assert_eq!(::from_asn1::<ObjectIdentifier>(&expected).unwrap(), oid);
+    }
+}
@@ -0,0 +1,64 @@
+use lib::Cow;
+use io;
+
+use super::read;
+
+/// Input of the DER deserializer.
+pub trait Source<'de>: io::Read {
+    /// Read next `len` bytes, borrowing them from the input if it is kept in memory.
+    fn read_bytes(&mut self, len: usize) -> io::Result<Cow<'de, [u8]>>;
+
+    /// Skip next `len` bytes.
+    fn skip_bytes(&mut self, len: usize) -> io::Result<()> {
+        read::skip(self, len)
+    }
+}
+
+impl<'de> Source<'de> for &'de [u8] {
+    fn read_bytes(&mut self, len: usize) -> io::Result<Cow<'de, [u8]>> {
+        if len > self.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof));
+        }
+
+        let (bytes, rest) = self.split_at(len);
+        *self = rest;
+        Ok(Cow::Borrowed(bytes))
+    }
+
+    fn skip_bytes(&mut self, len: usize) -> io::Result<()> {
+        self.read_bytes(len).map(|_| ())
+    }
+}
+
+/// Source reading from `std::io::Read`, values are always copied.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoSource<R> {
+    inner: R,
+}
+
+#[cfg(feature = "std")]
+impl<R: ::std::io::Read> IoSource<R> {
+    pub fn new(inner: R) -> Self {
+        IoSource { inner: inner }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: ::std::io::Read> io::Read for IoSource<R> {
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf).map_err(io::Error::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: ::std::io::Read> Source<'de> for IoSource<R> {
+    fn read_bytes(&mut self, len: usize) -> io::Result<Cow<'de, [u8]>> {
+        read::read_octet_string(self, len).map(Cow::Owned)
+    }
+}
@@ -0,0 +1,149 @@
+use lib::marker::PhantomData;
+
+use io;
+use de::Asn1Deserialize;
+
+use super::reader::{Deserializer, DecodeError};
+use super::source::Source;
+#[cfg(feature = "std")]
+use super::source::IoSource;
+
+/// Decoder of consecutive top-level values, like PDUs of a TCP stream or records of a log file.
+#[derive(Debug)]
+pub struct StreamDecoder<S> {
+    de: Deserializer<S>,
+}
+
+#[cfg(feature = "std")]
+impl<R: ::std::io::Read> StreamDecoder<IoSource<R>> {
+    /// Create decoder reading from `reader`.
+    pub fn from_reader(reader: R) -> Self {
+        StreamDecoder::new(IoSource::new(reader))
+    }
+}
+
+impl<'de, S: Source<'de>> StreamDecoder<S> {
+    /// Create decoder reading from `source`.
+    pub fn new(source: S) -> Self {
+        StreamDecoder { de: Deserializer::new(source) }
+    }
+
+    /// Offset of the next value in the input.
+    pub fn offset(&self) -> usize {
+        self.de.position()
+    }
+
+    /// Decode next value, returns `None` if the input ended cleanly before it.
+    /// Input ending inside the value is reported as `DecodeError::Truncated`.
+    pub fn decode<T: Asn1Deserialize<'de>>(&mut self) -> Result<Option<T>, DecodeError> {
+        let offset = self.offset();
+        let result = match self.de.at_end() {
+            Ok(true) => return Ok(None),
+            Ok(false) => self.de.decode().map(Some),
+            Err(e) => Err(e),
+        };
+
+        result.map_err(|e| match *e.kind() {
+                           DecodeError::IO(ref io_err) if io_err.kind() ==
+                                                          io::ErrorKind::UnexpectedEof => {
+                               DecodeError::Truncated(offset)
+                           }
+                           _ => e,
+                       })
+    }
+
+    /// Iterate over the remaining values, yielding each with its offset.
+    pub fn iter<T: Asn1Deserialize<'de>>(&mut self) -> StreamIter<S, T> {
+        StreamIter {
+            decoder: self,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Iterator over values of a `StreamDecoder`, stops after the first error.
+#[derive(Debug)]
+pub struct StreamIter<'a, S: 'a, T> {
+    decoder: &'a mut StreamDecoder<S>,
+    done: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, 'de, S: Source<'de>, T: Asn1Deserialize<'de>> Iterator for StreamIter<'a, S, T> {
+    type Item = Result<(usize, T), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let offset = self.decoder.offset();
+        match self.decoder.decode() {
+            Ok(Some(value)) => Some(Ok((offset, value))),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::StreamDecoder;
+    use der::DecodeError;
+    use universal::OctetString;
+
+    #[test]
+    fn stream_values() {
+        let mut buf = ::to_asn1(&5u32).unwrap();
+        buf.extend(::to_asn1(&vec![1u8, 2]).unwrap());
+        buf.extend(::to_asn1(&-300i32).unwrap());
+
+        let mut decoder = StreamDecoder::new(buf.as_slice());
+        assert_eq!(decoder.decode::<u32>().unwrap(), Some(5));
+        assert_eq!(decoder.offset(), 3);
+        assert_eq!(decoder.decode::<Vec<u8>>().unwrap(), Some(vec![1, 2]));
+        assert_eq!(decoder.decode::<i32>().unwrap(), Some(-300));
+        assert_eq!(decoder.decode::<i32>().unwrap(), None);
+    }
+
+    #[test]
+    fn stream_iter() {
+        let values: Vec<OctetString> = (0..3).map(|i| OctetString::new(vec![i; 200])).collect();
+        let mut buf = Vec::new();
+        for v in values.iter() {
+            buf.extend(::to_asn1(v).unwrap());
+        }
+
+        let mut decoder = StreamDecoder::from_reader(Cursor::new(&buf));
+        let out: Vec<(usize, OctetString)> = decoder.iter().map(|v| v.unwrap()).collect();
+        assert_eq!(out.iter().map(|v| v.0).collect::<Vec<_>>(), vec![0, 203, 406]);
+        assert_eq!(out.into_iter().map(|v| v.1).collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn stream_truncated() {
+        let mut buf = ::to_asn1(&5u32).unwrap();
+        buf.extend(::to_asn1(&vec![1u8, 2]).unwrap());
+        let len = buf.len();
+
+        for &cut in [4, 5, len - 1].iter() {
+            let mut decoder = StreamDecoder::from_reader(Cursor::new(&buf[..cut]));
+            let mut iter = decoder.iter::<::Value>();
+            assert!(iter.next().unwrap().is_ok());
+            match iter.next() {
+                Some(Err(DecodeError::Truncated(3))) => {}
+                other => panic!("unexpected {:?}", other),
+            }
+            assert!(iter.next().is_none());
+        }
+    }
+}
@@ -0,0 +1,487 @@
+use lib::{cmp, Vec};
+
+use io;
+use info::{Tag, Len};
+use info::{TAG_BOOLEAN, TAG_INTEGER, TAG_NULL, TAG_OBJECT_IDENTIFIER, TAG_OCTET_STRING,
+           TAG_SEQUENCE};
+
+use super::frame::ber_frame_len;
+use super::push::Status;
+use super::read::{self, ReadError};
+use super::reader::DecodeError;
+use super::write;
+
+/// Token of the TLV structure of the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token<'a> {
+    /// Start of a constructed value at `offset`, its contents follow as tokens up to the
+    /// matching `End`.
+    Start { tag: Tag, len: Len, offset: usize },
+    /// Primitive value.
+    Primitive { tag: Tag, content: &'a [u8] },
+    /// End of the innermost constructed value.
+    End,
+}
+
+/// Pull reader of the TLV structure of a slice, for scanning the input without decoding it.
+///
+/// Lengths are checked against the enclosing values. Indefinite lengths (BER) are accepted for
+/// constructed values.
+#[derive(Debug, Clone)]
+pub struct TlvReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// Ends of open constructed values, `None` if indefinite.
+    open: Vec<Option<usize>>,
+}
+
+impl<'a> TlvReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        TlvReader {
+            buf: buf,
+            pos: 0,
+            open: Vec::new(),
+        }
+    }
+
+    /// Offset of the next token in the input.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of open constructed values.
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Read next token, returns `None` at the end of the input.
+    pub fn next_token(&mut self) -> Result<Option<Token<'a>>, DecodeError> {
+        if self.at_end_of_value() {
+            if let Some(None) = self.open.pop() {
+                // end-of-contents of an indefinite value is checked against its enclosing value
+                self.pos = self.check_end(self.pos, 2)?;
+            }
+            return Ok(Some(Token::End));
+        }
+        if self.pos == self.buf.len() {
+            return if self.open.is_empty() {
+                       Ok(None)
+                   } else {
+                       Err(DecodeError::Truncated(self.pos))
+                   };
+        }
+
+        let offset = self.pos;
+        let (tag, len, content) = self.read_header()?;
+        if !tag.is_constructed() {
+            let len = match len {
+                Len::Def(len) => len,
+                Len::Indef => {
+                    return Err(DecodeError::InvalidLength("indefinite length of primitive value"))
+                }
+            };
+            let end = self.check_end(content, len)?;
+            self.pos = end;
+            return Ok(Some(Token::Primitive {
+                                tag: tag,
+                                content: &self.buf[content..end],
+                            }));
+        }
+
+        let end = match len {
+            Len::Def(len) => Some(self.check_end(content, len)?),
+            Len::Indef => None,
+        };
+        self.pos = content;
+        self.open.push(end);
+        Ok(Some(Token::Start {
+                    tag: tag,
+                    len: len,
+                    offset: offset,
+                }))
+    }
+
+    /// Tag of the next value, `None` at the end of the input or of the enclosing value.
+    pub fn peek_tag(&self) -> Result<Option<Tag>, DecodeError> {
+        if self.at_end_of_value() || self.pos == self.buf.len() {
+            return Ok(None);
+        }
+        self.header_at(self.pos).map(|(tag, _, _)| Some(tag))
+    }
+
+    /// Skip the next value with all of its contents, its definite lengths are not descended
+    /// into. Returns `false` at the end of the input or of the enclosing value.
+    pub fn skip(&mut self) -> Result<bool, DecodeError> {
+        if self.at_end_of_value() || self.pos == self.buf.len() {
+            return Ok(false);
+        }
+
+        let offset = self.pos;
+        let (_, len, content) = self.read_header()?;
+        let end = match len {
+            Len::Def(len) => self.check_end(content, len)?,
+            Len::Indef => {
+                match ber_frame_len(&self.buf[offset..])? {
+                    Status::Value(len) => self.check_end(offset, len)?,
+                    Status::NeedMore(_) => return Err(DecodeError::Truncated(offset)),
+                }
+            }
+        };
+        self.pos = end;
+        Ok(true)
+    }
+
+    /// Check if the innermost open value ends at the current position.
+    fn at_end_of_value(&self) -> bool {
+        match self.open.last() {
+            Some(&Some(end)) => self.pos >= end,
+            Some(&None) => self.buf[self.pos..].starts_with(&[0, 0]),
+            None => false,
+        }
+    }
+
+    /// Read the header at the current position, returns the content offset with it.
+    fn read_header(&mut self) -> Result<(Tag, Len, usize), DecodeError> {
+        let header = self.header_at(self.pos)?;
+        self.pos = header.2;
+        Ok(header)
+    }
+
+    /// Parse the header at `pos`, returns the content offset with it.
+    fn header_at(&self, pos: usize) -> Result<(Tag, Len, usize), DecodeError> {
+        let mut header = &self.buf[pos..];
+        let (tag, len) = match read::read_header(&mut header) {
+            Ok(header) => header,
+            Err(ReadError::IoError(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(DecodeError::Truncated(pos))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if tag == Tag::zero() {
+            return Err(DecodeError::InvalidTag("unexpected end-of-contents"));
+        }
+        Ok((tag, len, self.buf.len() - header.len()))
+    }
+
+    /// End of the value with content at `content` of length `len`, checked against the
+    /// enclosing value and the input.
+    fn check_end(&self, content: usize, len: usize) -> Result<usize, DecodeError> {
+        let end = content.saturating_add(len);
+        match self.open.last() {
+            Some(&Some(outer)) if end > outer => {
+                Err(DecodeError::InvalidLength("value exceeds enclosing value"))
+            }
+            _ if end > self.buf.len() => Err(DecodeError::Truncated(content)),
+            _ => Ok(end),
+        }
+    }
+}
+
+/// Builder of hand-crafted encodings, like test vectors and malformed inputs for decoders.
+///
+/// Lengths are computed automatically and encoded canonically unless `long_lengths` or
+/// `constructed_indefinite` ask for non-canonical forms.
+#[derive(Debug, Clone, Default)]
+pub struct TlvBuilder {
+    buf: Vec<u8>,
+    /// Number of octets of long form lengths, 0 for the canonical form.
+    len_octets: usize,
+}
+
+impl TlvBuilder {
+    pub fn new() -> Self {
+        TlvBuilder::default()
+    }
+
+    /// Encode lengths of the following values in long form with at least `octets` length
+    /// octets, 0 restores the canonical form.
+    pub fn long_lengths(&mut self, octets: usize) -> &mut Self {
+        assert!(octets <= 8);
+        self.len_octets = octets;
+        self
+    }
+
+    /// Append value with `tag` and `content`.
+    pub fn primitive(&mut self, tag: Tag, content: &[u8]) -> &mut Self {
+        self.header(&tag, content.len());
+        self.buf.extend_from_slice(content);
+        self
+    }
+
+    /// Append constructed value with `tag`, its contents are appended by `f`.
+    pub fn constructed<F>(&mut self, tag: Tag, f: F) -> &mut Self
+        where F: FnOnce(&mut TlvBuilder)
+    {
+        let content = self.nested(f);
+        self.header(&tag, content.len());
+        self.buf.extend_from_slice(&content);
+        self
+    }
+
+    /// Append constructed value with `tag` and indefinite length (BER), its contents are
+    /// appended by `f`.
+    pub fn constructed_indefinite<F>(&mut self, tag: Tag, f: F) -> &mut Self
+        where F: FnOnce(&mut TlvBuilder)
+    {
+        let content = self.nested(f);
+        write::write_header(&mut self.buf, &tag, &Len::Indef).expect("writing to Vec");
+        self.buf.extend_from_slice(&content);
+        self.buf.extend_from_slice(&[0, 0]);
+        self
+    }
+
+    pub fn boolean(&mut self, value: bool) -> &mut Self {
+        self.primitive(TAG_BOOLEAN, &[if value { 0xff } else { 0x00 }])
+    }
+
+    pub fn integer(&mut self, value: i64) -> &mut Self {
+        self.padded_integer(value, 0)
+    }
+
+    /// Append INTEGER `value` prefixed with `padding` redundant sign octets.
+    pub fn padded_integer(&mut self, value: i64, padding: usize) -> &mut Self {
+        let bytes = value.to_be_bytes();
+        let mut start = 0;
+        while start < bytes.len() - 1 &&
+              (bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0 ||
+               bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0) {
+            start += 1;
+        }
+
+        let sign = if value < 0 { 0xff } else { 0x00 };
+        let mut content = vec![sign; padding];
+        content.extend_from_slice(&bytes[start..]);
+        self.primitive(TAG_INTEGER, &content)
+    }
+
+    pub fn octet_string(&mut self, value: &[u8]) -> &mut Self {
+        self.primitive(TAG_OCTET_STRING, value)
+    }
+
+    pub fn null(&mut self) -> &mut Self {
+        self.primitive(TAG_NULL, &[])
+    }
+
+    /// Append OBJECT IDENTIFIER of `arcs`.
+    ///
+    /// Panics if there are less than two arcs, or the first two do not fit into one `u64` arc.
+    pub fn oid(&mut self, arcs: &[u64]) -> &mut Self {
+        assert!(arcs.len() >= 2, "OBJECT IDENTIFIER needs at least two arcs");
+        let first = arcs[0]
+            .checked_mul(40)
+            .and_then(|v| v.checked_add(arcs[1]))
+            .expect("first two OBJECT IDENTIFIER arcs do not fit");
+
+        let mut content = Vec::new();
+        write::write_base128(&mut content, first).expect("writing to Vec");
+        for &arc in &arcs[2..] {
+            write::write_base128(&mut content, arc).expect("writing to Vec");
+        }
+        self.primitive(TAG_OBJECT_IDENTIFIER, &content)
+    }
+
+    pub fn sequence<F>(&mut self, f: F) -> &mut Self
+        where F: FnOnce(&mut TlvBuilder)
+    {
+        self.constructed(TAG_SEQUENCE, f)
+    }
+
+    /// Append `bytes` as is.
+    pub fn raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Build contents with a builder of the same settings.
+    fn nested<F: FnOnce(&mut TlvBuilder)>(&self, f: F) -> Vec<u8> {
+        let mut nested = TlvBuilder {
+            buf: Vec::new(),
+            len_octets: self.len_octets,
+        };
+        f(&mut nested);
+        nested.buf
+    }
+
+    fn header(&mut self, tag: &Tag, len: usize) {
+        if self.len_octets == 0 {
+            write::write_header(&mut self.buf, tag, &Len::Def(len)).expect("writing to Vec");
+            return;
+        }
+
+        write::write_tag(&mut self.buf, tag).expect("writing to Vec");
+        let bytes = (len as u64).to_be_bytes();
+        let needed = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        let start = cmp::min(needed, bytes.len() - self.len_octets);
+        self.buf.push(0x80 | (bytes.len() - start) as u8);
+        self.buf.extend_from_slice(&bytes[start..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TlvBuilder, TlvReader, Token};
+    use der::DecodeError;
+    use info::{Asn1Tagged, Len, TAG_SEQUENCE};
+    use universal::{ObjectIdentifier, OctetString};
+
+    fn tokens_of(buf: &[u8]) -> Result<Vec<Token>, DecodeError> {
+        let mut reader = TlvReader::new(buf);
+        let mut tokens = Vec::new();
+        while let Some(token) = reader.next_token()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    #[test]
+    fn tokens() {
+        let buf = ::to_asn1(&vec![vec![1u32, 2], vec![]]).unwrap();
+        let seq = Vec::<u32>::asn1_tag();
+        let int = u32::asn1_tag();
+        assert_eq!(tokens_of(&buf).unwrap(),
+                   vec![Token::Start {
+                            tag: seq,
+                            len: Len::Def(10),
+                            offset: 0,
+                        },
+                        Token::Start {
+                            tag: seq,
+                            len: Len::Def(6),
+                            offset: 2,
+                        },
+                        Token::Primitive {
+                            tag: int,
+                            content: &[1],
+                        },
+                        Token::Primitive {
+                            tag: int,
+                            content: &[2],
+                        },
+                        Token::End,
+                        Token::Start {
+                            tag: seq,
+                            len: Len::Def(0),
+                            offset: 10,
+                        },
+                        Token::End,
+                        Token::End]);
+
+        let buf = ::to_asn1(&OctetString::new(vec![0xaa, 0xbb])).unwrap();
+        assert_eq!(tokens_of(&buf).unwrap(),
+                   vec![Token::Primitive {
+                            tag: OctetString::asn1_tag(),
+                            content: &[0xaa, 0xbb],
+                        }]);
+    }
+
+    #[test]
+    fn indefinite() {
+        let buf = [0x30, 0x80, 0x30, 0x80, 0x02, 0x01, 0x01, 0x00, 0x00, 0x04, 0x01, 0xaa, 0x00,
+                   0x00, 0x05, 0x00];
+        let mut reader = TlvReader::new(&buf);
+        let mut ends = 0;
+        while reader.position() < 14 {
+            if reader.next_token().unwrap() == Some(Token::End) {
+                ends += 1;
+            }
+        }
+        assert_eq!(ends, 2);
+        assert_eq!(reader.depth(), 0);
+
+        let mut reader = TlvReader::new(&buf);
+        reader.next_token().unwrap();
+        assert_eq!(reader.peek_tag().unwrap(), Some(Vec::<u32>::asn1_tag()));
+        assert!(reader.skip().unwrap());
+        assert_eq!(reader.position(), 9);
+        assert!(reader.skip().unwrap());
+        assert!(!reader.skip().unwrap());
+        assert_eq!(reader.next_token().unwrap(), Some(Token::End));
+        assert!(reader.skip().unwrap());
+        assert_eq!(reader.next_token().unwrap(), None);
+    }
+
+    #[test]
+    fn malformed() {
+        // element longer than its enclosing value
+        let mut reader = TlvReader::new(&[0x30, 0x03, 0x04, 0x02, 0xaa, 0xbb]);
+        reader.next_token().unwrap();
+        match reader.next_token() {
+            Err(DecodeError::InvalidLength(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let mut reader = TlvReader::new(&[0x30, 0x04, 0x04, 0x02, 0xaa]);
+        match reader.skip() {
+            Err(DecodeError::Truncated(2)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match tokens_of(&[0x30, 0x80, 0x05, 0x00]) {
+            Err(DecodeError::Truncated(4)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match tokens_of(&[0x04, 0x80, 0x00, 0x00]) {
+            Err(DecodeError::InvalidLength(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // end-of-contents of an indefinite value past the end of its enclosing value
+        let mut reader = TlvReader::new(&[0x30, 0x02, 0x30, 0x80, 0x00, 0x00, 0x05, 0x00]);
+        reader.next_token().unwrap();
+        reader.next_token().unwrap();
+        match reader.next_token() {
+            Err(DecodeError::InvalidLength(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(reader.position(), 4);
+    }
+
+    #[test]
+    fn builder() {
+        let mut b = TlvBuilder::new();
+        b.sequence(|b| {
+                       b.integer(0).integer(128).integer(-129);
+                   })
+            .oid(&[1, 2, 840, 113549])
+            .octet_string(&[0xaa])
+            .null();
+        assert_eq!(b.as_slice(),
+                   &[0x30, 0x0b, 0x02, 0x01, 0x00, 0x02, 0x02, 0x00, 0x80, 0x02, 0x02, 0xff, 0x7f,
+                     0x06, 0x06, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x04, 0x01, 0xaa, 0x05, 0x00]
+                           [..]);
+
+        let oid = ObjectIdentifier::new(vec![1, 2, 840, 113549]);
+        assert_eq!(::from_asn1::<ObjectIdentifier>(&b.as_slice()[13..21]).unwrap(), oid);
+        assert_eq!(::from_asn1::<Vec<i64>>(&b.as_slice()[..13]).unwrap(), vec![0, 128, -129]);
+    }
+
+    #[test]
+    fn builder_non_canonical() {
+        let mut b = TlvBuilder::new();
+        b.long_lengths(2).integer(1).long_lengths(0).padded_integer(-1, 2);
+        assert_eq!(b.as_slice(),
+                   &[0x02, 0x82, 0x00, 0x01, 0x01, 0x02, 0x03, 0xff, 0xff, 0xff][..]);
+
+        let mut b = TlvBuilder::new();
+        b.constructed_indefinite(TAG_SEQUENCE, |b| {
+            b.integer(7).raw(&[0x02, 0x01, 0x08]);
+        });
+        let buf = b.into_vec();
+        assert_eq!(buf, vec![0x30, 0x80, 0x02, 0x01, 0x07, 0x02, 0x01, 0x08, 0x00, 0x00]);
+        assert!(::from_asn1::<Vec<u32>>(&buf).is_err());
+        assert_eq!(tokens_of(&buf).unwrap().len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "first two OBJECT IDENTIFIER arcs do not fit")]
+    fn builder_oid_overflow() {
+        TlvBuilder::new().oid(&[2, u64::max_value()]);
+    }
+}
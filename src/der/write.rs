@@ -1,5 +1,5 @@
-use std::slice;
-use std::io::{Result as IoResult, Write};
+use lib::slice;
+use io::{Result as IoResult, Write};
 
 use info::tag::{Tag, Len, LenNum};
 
@@ -25,9 +25,21 @@ pub fn write_byte<W: Write>(w: &mut W, byte: u8) -> IoResult<()> {
     w.write_all(&[byte])
 }
 
+/// Number of octets of `n` in base 128.
+#[inline]
+pub fn base128_len(n: u64) -> usize {
+    let mut len = 1;
+    let mut n = n >> 7;
+    while n > 0 {
+        len += 1;
+        n >>= 7;
+    }
+    len
+}
+
 #[inline]
 pub fn write_base128<W: Write>(w: &mut W, u: u64) -> IoResult<()> {
-    use std::mem;
+    use lib::mem;
 
     let mut buf: [u8; 9] = unsafe { mem::uninitialized() };
     let from = unsafe { _rwrite_base128(buf.as_mut_ptr(), 8, u) };
@@ -180,14 +192,79 @@ integer_write!(write_u32, u32, 4);
 integer_write!(write_u64, u64, 8);
 integer_write!(write_usize, usize, 8);
 
-#[allow(dead_code)]
-pub fn write_real32<W: Write>(_w: &mut W, _tag: &Tag, _value: f32) -> IoResult<()> {
-    unimplemented!()
+/// Max REAL content length: header, 2 exponent octets and 53 bit mantissa.
+const MAX_REAL_LEN: usize = 10;
+
+/// Encode REAL content octets into the end of `buf`, returns start position.
+/// DER uses base 2 with an odd mantissa and no scaling (X.690 11.3.1).
+fn _rwrite_real(buf: &mut [u8; MAX_REAL_LEN], value: f64) -> usize {
+    let end = buf.len();
+
+    if value == 0.0 {
+        if value.is_sign_negative() {
+            buf[end - 1] = 0x43;
+            return end - 1;
+        }
+        return end;
+    } else if value.is_nan() {
+        buf[end - 1] = 0x42;
+        return end - 1;
+    } else if value.is_infinite() {
+        buf[end - 1] = if value > 0.0 { 0x40 } else { 0x41 };
+        return end - 1;
+    }
+
+    let bits = value.to_bits();
+    let biased = ((bits >> 52) & 0x7ff) as i64;
+    let fraction = bits & 0x000f_ffff_ffff_ffff;
+
+    let (mut mantissa, mut exponent) = if biased == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << 52), biased - 1075)
+    };
+    while mantissa & 1 == 0 {
+        mantissa >>= 1;
+        exponent += 1;
+    }
+
+    let mut pos = end;
+    while mantissa > 0 {
+        pos -= 1;
+        buf[pos] = mantissa as u8;
+        mantissa >>= 8;
+    }
+
+    let mut head = if value.is_sign_negative() { 0xc0 } else { 0x80 };
+    if -0x80 <= exponent && exponent < 0x80 {
+        pos -= 1;
+        buf[pos] = exponent as u8;
+    } else {
+        pos -= 2;
+        buf[pos] = (exponent >> 8) as u8;
+        buf[pos + 1] = exponent as u8;
+        head |= 0x01;
+    }
+
+    pos -= 1;
+    buf[pos] = head;
+    pos
+}
+
+/// Length of REAL content octets.
+pub fn real_len(value: f64) -> usize {
+    let mut buf = [0u8; MAX_REAL_LEN];
+    MAX_REAL_LEN - _rwrite_real(&mut buf, value)
+}
+
+pub fn write_real32<W: Write>(w: &mut W, tag: &Tag, value: f32) -> IoResult<()> {
+    write_real64(w, tag, value as f64)
 }
 
-#[allow(dead_code)]
-pub fn write_real64<W: Write>(_w: &mut W, _tag: &Tag, _value: f64) -> IoResult<()> {
-    unimplemented!()
+pub fn write_real64<W: Write>(w: &mut W, tag: &Tag, value: f64) -> IoResult<()> {
+    let mut buf = [0u8; MAX_REAL_LEN];
+    let from = _rwrite_real(&mut buf, value);
+    write_primitive(w, tag, &buf[from..])
 }
 
 pub fn write_bit_string<W: Write>(w: &mut W,
@@ -213,21 +290,47 @@ pub fn write_null<W: Write>(w: &mut W, tag: &Tag) -> IoResult<()> {
     _write_header(w, tag, 0)
 }
 
+/// First subidentifier of OBJECT IDENTIFIER `arcs`, holding the first two arcs. `None` if there
+/// are less than two arcs or they are out of range.
+pub fn first_subidentifier(arcs: &[u64]) -> Option<u64> {
+    if arcs.len() < 2 {
+        return None;
+    }
+    match arcs[0] {
+        0 | 1 if arcs[1] < 40 => Some(arcs[0] * 40 + arcs[1]),
+        2 => arcs[1].checked_add(80),
+        _ => None,
+    }
+}
+
+/// Write content octets of OBJECT IDENTIFIER `arcs`, see `first_subidentifier`.
+pub fn write_object_identifier_content<W: Write>(w: &mut W,
+                                                 first: u64,
+                                                 tail: &[u64])
+                                                 -> IoResult<()> {
+    write_base128(w, first)?;
+    for &oi in tail {
+        write_base128(w, oi)?
+    }
+    Ok(())
+}
+
 // TODO: use this as optimization for short oids
 pub fn write_short_object_identifier<W: Write>(w: &mut W,
                                                tag: &Tag,
                                                value: &[u64])
                                                -> IoResult<()> {
     assert!(value.len() >= 2 && value.len() <= 130);
+    let first = first_subidentifier(value).expect("invalid first arcs of OBJECT IDENTIFIER");
 
     const MAX_LEN: usize = 128;
     const MAX_BUF_SIZE: usize = MAX_LEN * 9;
 
-    let (head, tail) = value.split_at(2);
+    let tail = &value[2..];
 
     let mut _write = |v: &[u8]| -> IoResult<()> {
-        _write_header(w, tag, v.len() + 1)?;
-        write_byte(w, head[0] as u8 * 40 + head[1] as u8)?;
+        _write_header(w, tag, v.len() + base128_len(first))?;
+        write_base128(w, first)?;
         w.write_all(v)
     };
 
@@ -246,19 +349,17 @@ pub fn write_short_object_identifier<W: Write>(w: &mut W,
     }
 }
 
+/// Write OBJECT IDENTIFIER of any number of arcs straight into `w`, without a buffer.
+///
+/// Panics if the first two arcs are not valid, see `first_subidentifier`.
 pub fn write_object_identifier<W: Write>(w: &mut W, tag: &Tag, value: &[u64]) -> IoResult<()> {
-    assert!(value.len() >= 2);
-
-    let mut buf: Vec<u8> = Vec::with_capacity(1280);
-    let (head, tail) = value.split_at(2);
+    let first = first_subidentifier(value).expect("invalid first arcs of OBJECT IDENTIFIER");
 
-    for &oi in tail {
-        write_base128(&mut buf, oi)?
-    }
+    let tail = &value[2..];
+    let tail_len: usize = tail.iter().map(|&oi| base128_len(oi)).sum();
 
-    _write_header(w, tag, buf.len() + 1)?;
-    write_byte(w, (head[0] as u8) * 40 + (head[1] as u8))?;
-    w.write_all(buf.as_slice())
+    _write_header(w, tag, base128_len(first) + tail_len)?;
+    write_object_identifier_content(w, first, tail)
 }
 
 #[cfg(test)]
@@ -345,6 +446,22 @@ mod tests {
         buffer_eq_test(&test_set[..], |buf, x| write_u64(buf, &tag, *x))
     }
 
+    #[test]
+    fn real_write() {
+        let tag = Tag::primitive(Class::Universal, 0x09);
+        let test_set = [(0.0, vec![0x09, 0x00]),
+                        (-0.0, vec![0x09, 0x01, 0x43]),
+                        (1.0, vec![0x09, 0x03, 0x80, 0x00, 0x01]),
+                        (0.5, vec![0x09, 0x03, 0x80, 0xff, 0x01]),
+                        (-3.25, vec![0x09, 0x03, 0xc0, 0xfe, 0x0d]),
+                        (1024.0, vec![0x09, 0x03, 0x80, 0x0a, 0x01]),
+                        (5e-324, vec![0x09, 0x04, 0x81, 0xfb, 0xce, 0x01]),
+                        (::std::f64::INFINITY, vec![0x09, 0x01, 0x40]),
+                        (::std::f64::NEG_INFINITY, vec![0x09, 0x01, 0x41])];
+
+        buffer_eq_test(&test_set[..], |buf, x| write_real64(buf, &tag, *x))
+    }
+
     #[test]
     fn bits_write() {
         let test_set = [((vec![0xff, 0xff, 0xf0], 4), vec![0x03, 0x04, 0x04, 0xff, 0xff, 0xf0]),
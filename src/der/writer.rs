@@ -1,9 +1,14 @@
-use std::io::{Error as IoError, Write};
+use lib::{fmt, String, Vec};
+use io::Error as IoError;
 
-use info::{self, Tag, Len};
+use info::{self, Tag};
 use ser::{self, SeqSerializer};
 
+use super::read;
 use super::write;
+use super::sink::{LengthPatcher, Sink};
+#[cfg(feature = "std")]
+use super::sink::IoSink;
 
 #[derive(Debug)]
 pub enum EncodeError {
@@ -11,10 +16,42 @@ pub enum EncodeError {
     InvalidLength,
     InvalidValue,
     PrimUntagged,
+    /// Output buffer can not hold the encoding of `needed` bytes.
+    BufferTooSmall { needed: usize },
+    /// Encoding of the given length exceeds the maximum frame size.
+    FrameTooLarge(usize),
     Custom(String),
     GeneralIO(IoError),
 }
 
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EncodeError::InvalidTag => f.write_str("invalid tag"),
+            EncodeError::InvalidLength => f.write_str("invalid length"),
+            EncodeError::InvalidValue => f.write_str("invalid value"),
+            EncodeError::PrimUntagged => f.write_str("untagged primitive value"),
+            EncodeError::BufferTooSmall { needed } => {
+                write!(f, "buffer is too small, {} bytes needed", needed)
+            }
+            EncodeError::FrameTooLarge(len) => {
+                write!(f, "frame of {} bytes exceeds the maximum size", len)
+            }
+            EncodeError::Custom(ref msg) => f.write_str(msg),
+            EncodeError::GeneralIO(ref e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for EncodeError {
+    fn source(&self) -> Option<&(dyn (::std::error::Error) + 'static)> {
+        match *self {
+            EncodeError::GeneralIO(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<IoError> for EncodeError {
     fn from(e: IoError) -> Self {
@@ -22,6 +59,13 @@ impl From<IoError> for EncodeError {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<::std::io::Error> for EncodeError {
+    fn from(e: ::std::io::Error) -> Self {
+        EncodeError::GeneralIO(e.into())
+    }
+}
+
 impl ser::Error for EncodeError {
     fn invalid_tag() -> Self {
         EncodeError::InvalidTag
@@ -40,168 +84,250 @@ impl ser::Error for EncodeError {
     }
 }
 
-pub struct StructSerializer<W> {
-    serializer: Vec<u8>,
-    out_encoder: Serializer<W>,
+/// Serializer of SEQUENCE fields, writing them straight into the output of the parent.
+pub struct StructSerializer<H> {
+    serializer: H,
+    mark: usize,
 }
 
-impl<W: Write> StructSerializer<W> {
-    pub fn next<'a>(&'a mut self) -> Serializer<&'a mut Vec<u8>> {
-        Serializer::new(&mut self.serializer)
+impl<S: Sink> SeqSerializer for StructSerializer<Serializer<S>> {
+    type Ok = ();
+    type Err = EncodeError;
+
+    fn serialize_field<V: ser::Asn1Serialize + ?Sized>(&mut self,
+                                                       value: &V)
+                                                       -> Result<(), Self::Err> {
+        value.asn1_serialize(&mut self.serializer)
+    }
+
+    fn finish(mut self) -> Result<Self::Ok, Self::Err> {
+        let serializer = &mut self.serializer;
+        serializer.lengths.close(&mut serializer.writer, self.mark)?;
+        Ok(())
     }
 }
 
-impl<W: Write> SeqSerializer for StructSerializer<W> {
+impl<'a, S: Sink> SeqSerializer for StructSerializer<&'a mut Serializer<S>> {
     type Ok = ();
     type Err = EncodeError;
 
     fn serialize_field<V: ser::Asn1Serialize + ?Sized>(&mut self,
                                                        value: &V)
                                                        -> Result<(), Self::Err> {
-        value.asn1_serialize(self.next())
+        value.asn1_serialize(&mut *self.serializer)
     }
 
-    fn finish(mut self) -> Result<Self::Ok, Self::Err> {
-        let len = self.serializer.len();
-        let chunk = self.serializer.as_slice();
-
-        self.out_encoder
-            .override_tag(&info::TAG_SEQUENCE, |w, tag| {
-                write::write_header(w, tag, &Len::Def(len))?;
-                w.write_all(chunk)?;
-                Ok(())
-            })
+    fn finish(self) -> Result<Self::Ok, Self::Err> {
+        let serializer = &mut *self.serializer;
+        serializer.lengths.close(&mut serializer.writer, self.mark)?;
+        Ok(())
     }
 }
 
+/// Encode content octets of OBJECT IDENTIFIER `arcs`, as kept by `ObjectIdentifierRef`.
+pub fn object_identifier_content(arcs: &[u64]) -> Result<Vec<u8>, EncodeError> {
+    let first = write::first_subidentifier(arcs).ok_or(EncodeError::InvalidValue)?;
+    let mut out = Vec::with_capacity(arcs.len() + 1);
+    write::write_object_identifier_content(&mut out, first, &arcs[2..])?;
+    Ok(out)
+}
+
 #[derive(Debug)]
-pub struct Serializer<W> {
-    writer: W,
+pub struct Serializer<S> {
+    writer: S,
     implicit_tag: Option<Tag>,
+    lengths: LengthPatcher,
+}
+
+#[cfg(feature = "std")]
+impl<W: ::std::io::Write> Serializer<IoSink<W>> {
+    /// Create serializer writing to `writer`.
+    pub fn from_writer(writer: W) -> Self {
+        Serializer::new(IoSink::new(writer))
+    }
 }
 
-impl<'a, W: 'a> Serializer<W> {
-    pub fn new(inner: W) -> Self {
+impl<S> Serializer<S> {
+    pub fn new(inner: S) -> Self {
         Serializer {
             writer: inner,
             implicit_tag: None,
+            lengths: LengthPatcher::new(),
         }
     }
 
-    pub fn with_tag(self, tag: Tag) -> Self {
-        Serializer {
-            writer: self.writer,
-            implicit_tag: Some(tag),
-        }
+    pub fn with_tag(mut self, tag: Tag) -> Self {
+        self.implicit_tag = Some(tag);
+        self
     }
 
     fn override_tag<T, F>(&mut self, tag: &Tag, f: F) -> T
-        where F: FnOnce(&mut W, &Tag) -> T
+        where F: FnOnce(&mut S, &Tag) -> T
     {
-        let tag = if let Some(ref tag) = self.implicit_tag {
-            tag
-        } else {
-            tag
-        };
-        f(&mut self.writer, tag)
+        let tag = self.implicit_tag.take().unwrap_or(*tag);
+        f(&mut self.writer, &tag)
     }
 
-    pub fn into_inner(self) -> W {
+    pub fn into_inner(self) -> S {
         self.writer
     }
 }
 
-impl<W: Write> ser::Asn1Serializer for Serializer<W> {
+impl<S: Sink> Serializer<S> {
+    /// Write the tag of a constructed value and start its content.
+    fn open_constructed(&mut self, tag: &Tag) -> Result<usize, EncodeError> {
+        self.override_tag(tag, |w, tag| write::write_tag(w, tag))?;
+        Ok(self.lengths.open(&mut self.writer)?)
+    }
+}
+
+macro_rules! forward_to_ref {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method(mut self $(, $arg: $ty)*) -> Result<Self::Ok, Self::Err> {
+                (&mut self).$method($($arg),*)
+            }
+        )*
+    }
+}
+
+impl<S: Sink> ser::Asn1Serializer for Serializer<S> {
     type Ok = ();
     type Err = EncodeError;
 
-    type SeqSerializer = StructSerializer<W>;
-    type ImplicitSerializer = Serializer<W>;
-    type ExplicitSerializer = Serializer<W>;
+    type SeqSerializer = StructSerializer<Serializer<S>>;
+    type ImplicitSerializer = Serializer<S>;
+    type ExplicitSerializer = Serializer<S>;
+
+    forward_to_ref! {
+        serialize_bool(value: bool);
+        serialize_i8(value: i8);
+        serialize_i16(value: i16);
+        serialize_i32(value: i32);
+        serialize_i64(value: i64);
+        serialize_isize(value: isize);
+        serialize_u8(value: u8);
+        serialize_u16(value: u16);
+        serialize_u32(value: u32);
+        serialize_u64(value: u64);
+        serialize_usize(value: usize);
+        serialize_f32(value: f32);
+        serialize_f64(value: f64);
+        serialize_bit_string(value: (u8, &[u8]));
+        serialize_bytes(value: &[u8]);
+        serialize_null();
+        serialize_object_identifier(value: &[u64]);
+        serialize_raw(value: &[u8]);
+    }
+
+    fn serialize_tagged(self, tag: Tag) -> Result<Self::ExplicitSerializer, Self::Err> {
+        self.serialize_implicit(tag)
+    }
+
+    fn serialize_implicit(self, tag: Tag) -> Result<Self::ImplicitSerializer, Self::Err> {
+        Ok(self.with_tag(tag))
+    }
 
-    fn serialize_bool(mut self, value: bool) -> Result<Self::Ok, Self::Err> {
+    fn serialize_sequence(mut self) -> Result<Self::SeqSerializer, Self::Err> {
+        let mark = self.open_constructed(&info::TAG_SEQUENCE)?;
+        Ok(StructSerializer {
+               serializer: self,
+               mark: mark,
+           })
+    }
+}
+
+impl<'a, S: Sink> ser::Asn1Serializer for &'a mut Serializer<S> {
+    type Ok = ();
+    type Err = EncodeError;
+
+    type SeqSerializer = StructSerializer<&'a mut Serializer<S>>;
+    type ImplicitSerializer = Self;
+    type ExplicitSerializer = Self;
+
+    fn serialize_bool(self, value: bool) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_BOOLEAN, |w, tag| {
             write::write_boolean(w, tag, value)?;
             Ok(())
         })
     }
 
-    fn serialize_i8(mut self, value: i8) -> Result<Self::Ok, Self::Err> {
+    fn serialize_i8(self, value: i8) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_i8(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_i16(mut self, value: i16) -> Result<Self::Ok, Self::Err> {
+    fn serialize_i16(self, value: i16) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_i16(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_i32(mut self, value: i32) -> Result<Self::Ok, Self::Err> {
+    fn serialize_i32(self, value: i32) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_i32(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_i64(mut self, value: i64) -> Result<Self::Ok, Self::Err> {
+    fn serialize_i64(self, value: i64) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_i64(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_isize(mut self, value: isize) -> Result<Self::Ok, Self::Err> {
+    fn serialize_isize(self, value: isize) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_isize(w, tag, value)?;
             Ok(())
         })
     }
 
-    fn serialize_u8(mut self, value: u8) -> Result<Self::Ok, Self::Err> {
+    fn serialize_u8(self, value: u8) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_u8(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_u16(mut self, value: u16) -> Result<Self::Ok, Self::Err> {
+    fn serialize_u16(self, value: u16) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_u16(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_u32(mut self, value: u32) -> Result<Self::Ok, Self::Err> {
+    fn serialize_u32(self, value: u32) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_u32(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_u64(mut self, value: u64) -> Result<Self::Ok, Self::Err> {
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_u64(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_usize(mut self, value: usize) -> Result<Self::Ok, Self::Err> {
+    fn serialize_usize(self, value: usize) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_INTEGER, |w, tag| {
             write::write_usize(w, tag, value)?;
             Ok(())
         })
     }
 
-    fn serialize_f32(mut self, value: f32) -> Result<Self::Ok, Self::Err> {
+    fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_REAL, |w, tag| {
             write::write_real32(w, tag, value)?;
             Ok(())
         })
     }
-    fn serialize_f64(mut self, value: f64) -> Result<Self::Ok, Self::Err> {
+    fn serialize_f64(self, value: f64) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_REAL, |w, tag| {
             write::write_real64(w, tag, value)?;
             Ok(())
         })
     }
 
-    fn serialize_bit_string(mut self, value: (u8, &[u8])) -> Result<Self::Ok, Self::Err> {
+    fn serialize_bit_string(self, value: (u8, &[u8])) -> Result<Self::Ok, Self::Err> {
         let (unused, bytes) = value;
         if unused < 8 {
             self.override_tag(&info::TAG_BIT_STRING, |w, tag| {
@@ -213,21 +339,24 @@ impl<W: Write> ser::Asn1Serializer for Serializer<W> {
         }
     }
 
-    fn serialize_bytes(mut self, value: &[u8]) -> Result<Self::Ok, Self::Err> {
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_OCTET_STRING, |w, tag| {
             write::write_octet_string(w, tag, value)?;
             Ok(())
         })
     }
 
-    fn serialize_null(mut self) -> Result<Self::Ok, Self::Err> {
+    fn serialize_null(self) -> Result<Self::Ok, Self::Err> {
         self.override_tag(&info::TAG_NULL, |w, tag| {
             write::write_null(w, tag)?;
             Ok(())
         })
     }
 
-    fn serialize_object_identifier(mut self, value: &[u64]) -> Result<Self::Ok, Self::Err> {
+    fn serialize_object_identifier(self, value: &[u64]) -> Result<Self::Ok, Self::Err> {
+        if write::first_subidentifier(value).is_none() {
+            return Err(EncodeError::InvalidValue);
+        }
         self.override_tag(&info::TAG_OBJECT_IDENTIFIER,
                           |w, tag| if value.len() < 128 {
                               {
@@ -242,18 +371,41 @@ impl<W: Write> ser::Asn1Serializer for Serializer<W> {
                           })
     }
 
+    fn serialize_raw(self, value: &[u8]) -> Result<Self::Ok, Self::Err> {
+        match self.implicit_tag.take() {
+            None => {
+                self.writer.write_all(value)?;
+                Ok(())
+            }
+            Some(tag) => {
+                // re-tag the value, keeping its content
+                let mut content = value;
+                read::read_tag(&mut content).map_err(|_| EncodeError::InvalidValue)?;
+                let len = read::read_len_def(&mut content).map_err(|_| EncodeError::InvalidLength)?;
+                if len != content.len() {
+                    return Err(EncodeError::InvalidLength);
+                }
+
+                write::write_primitive(&mut self.writer, &tag, content)?;
+                Ok(())
+            }
+        }
+    }
+
     fn serialize_tagged(self, tag: Tag) -> Result<Self::ExplicitSerializer, Self::Err> {
         self.serialize_implicit(tag)
     }
 
     fn serialize_implicit(self, tag: Tag) -> Result<Self::ImplicitSerializer, Self::Err> {
-        Ok(self.with_tag(tag))
+        self.implicit_tag = Some(tag);
+        Ok(self)
     }
 
     fn serialize_sequence(self) -> Result<Self::SeqSerializer, Self::Err> {
+        let mark = self.open_constructed(&info::TAG_SEQUENCE)?;
         Ok(StructSerializer {
-               serializer: Vec::with_capacity(128),
-               out_encoder: self,
+               serializer: self,
+               mark: mark,
            })
     }
 }
@@ -2,7 +2,7 @@
 pub mod tag;
 pub mod universal;
 
-use std::fmt::{self, Display};
+use lib::fmt::{self, Display};
 
 pub use self::tag::{Class, Tag, Len, ContentType, TagNum, LenNum};
 pub use self::tag::Class::*;
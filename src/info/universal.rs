@@ -38,6 +38,11 @@ pub const TAG_REAL: Tag = Tag {
     tagnum: 0x09,
     content_type: Primitive,
 };
+pub const TAG_UTF8_STRING: Tag = Tag {
+    class: Class::Universal,
+    tagnum: 0x0c,
+    content_type: Primitive,
+};
 pub const TAG_SEQUENCE: Tag = Tag {
     class: Class::Universal,
     tagnum: 0x10,
@@ -49,6 +54,38 @@ pub const TAG_SET: Tag = Tag {
     content_type: Constructed,
 };
 
+pub const TYPE_ANY: &str = "ANY";
+pub const TAG_NUMERIC_STRING: Tag = Tag {
+    class: Class::Universal,
+    tagnum: 0x12,
+    content_type: Primitive,
+};
+pub const TAG_PRINTABLE_STRING: Tag = Tag {
+    class: Class::Universal,
+    tagnum: 0x13,
+    content_type: Primitive,
+};
+pub const TAG_IA5_STRING: Tag = Tag {
+    class: Class::Universal,
+    tagnum: 0x16,
+    content_type: Primitive,
+};
+pub const TAG_UTC_TIME: Tag = Tag {
+    class: Class::Universal,
+    tagnum: 0x17,
+    content_type: Primitive,
+};
+pub const TAG_GENERALIZED_TIME: Tag = Tag {
+    class: Class::Universal,
+    tagnum: 0x18,
+    content_type: Primitive,
+};
+pub const TAG_VISIBLE_STRING: Tag = Tag {
+    class: Class::Universal,
+    tagnum: 0x1a,
+    content_type: Primitive,
+};
+
 pub const TYPE_BOOLEAN: &str = "BOOLEAN";
 pub const TYPE_INTEGER: &str = "INTEGER";
 pub const TYPE_BIT_STRING: &str = "BIT STRING";
@@ -60,4 +97,11 @@ pub const TYPE_SEQUENCE: &str = "SEQUENCE";
 pub const TYPE_SEQUENCE_OF: &str = "SEQUENCE OF";
 pub const TYPE_SET: &str = "SET";
 pub const TYPE_SET_OF: &str = "SET OF";
+pub const TYPE_UTF8_STRING: &str = "UTF8String";
+pub const TYPE_NUMERIC_STRING: &str = "NumericString";
+pub const TYPE_PRINTABLE_STRING: &str = "PrintableString";
+pub const TYPE_IA5_STRING: &str = "IA5String";
+pub const TYPE_UTC_TIME: &str = "UTCTime";
+pub const TYPE_GENERALIZED_TIME: &str = "GeneralizedTime";
+pub const TYPE_VISIBLE_STRING: &str = "VisibleString";
 
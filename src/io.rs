@@ -0,0 +1,143 @@
+//! Byte input and output of the encoders and decoders.
+//!
+//! These are small replacements of `std::io::Read` and `std::io::Write` so the crate builds
+//! without `std`. Adapters for `std::io` are provided by `der::IoSource` and `der::IoSink`.
+use lib::{fmt, result, Vec};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Input ended in the middle of a value.
+    UnexpectedEof,
+    /// Output has no room left.
+    WriteZero,
+    /// Any other failure of the underlying reader or writer.
+    Other,
+}
+
+/// Error of the byte source or sink.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    #[cfg(feature = "std")]
+    inner: Option<::std::io::Error>,
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl Error {
+    /// Create new error of `kind`.
+    pub fn new(kind: ErrorKind) -> Self {
+        Error {
+            kind: kind,
+            #[cfg(feature = "std")]
+            inner: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Get the `std::io` error this one was converted from.
+    #[cfg(feature = "std")]
+    pub fn get_ref(&self) -> Option<&::std::io::Error> {
+        self.inner.as_ref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "std")]
+        {
+            if let Some(ref inner) = self.inner {
+                return inner.fmt(f);
+            }
+        }
+
+        match self.kind {
+            ErrorKind::UnexpectedEof => f.write_str("truncated value"),
+            ErrorKind::WriteZero => f.write_str("buffer is too small"),
+            ErrorKind::Other => f.write_str("i/o error"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<::std::io::Error> for Error {
+    fn from(e: ::std::io::Error) -> Self {
+        let kind = match e.kind() {
+            ::std::io::ErrorKind::UnexpectedEof => ErrorKind::UnexpectedEof,
+            ::std::io::ErrorKind::WriteZero => ErrorKind::WriteZero,
+            _ => ErrorKind::Other,
+        };
+        Error {
+            kind: kind,
+            inner: Some(e),
+        }
+    }
+}
+
+/// Source of bytes.
+pub trait Read {
+    /// Read exactly `buf.len()` bytes.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()>;
+}
+
+/// Sink of bytes.
+pub trait Write {
+    /// Write all of `buf`.
+    fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+}
+
+impl<'a> Read for &'a [u8] {
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        if buf.len() > self.len() {
+            return Err(Error::new(ErrorKind::UnexpectedEof));
+        }
+
+        let (head, rest) = self.split_at(buf.len());
+        buf.copy_from_slice(head);
+        *self = rest;
+        Ok(())
+    }
+}
+
+impl<'a, R: Read + ?Sized> Read for &'a mut R {
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        (**self).read_exact(buf)
+    }
+}
+
+impl Write for Vec<u8> {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+impl<'a, W: Write + ?Sized> Write for &'a mut W {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        (**self).write_all(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_read() {
+        let mut input = &[1u8, 2, 3][..];
+        let mut buf = [0u8; 2];
+        input.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2]);
+        assert_eq!(input.read_exact(&mut buf).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(test, feature(test))]
 #![cfg_attr(test, feature(plugin))]
 #![cfg_attr(test, plugin(quickcheck_macros))]
@@ -7,6 +8,10 @@
 #![cfg_attr(feature="clippy", feature(plugin))]
 #![cfg_attr(feature="clippy", plugin(clippy))]
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+
 #[cfg(feature = "with-serde")]
 extern crate serde;
 #[cfg(feature = "with-serde")]
@@ -15,23 +20,63 @@ extern crate serde_derive;
 #[cfg(feature = "with-serde")]
 extern crate serde_bytes;
 
+#[cfg(feature = "futures")]
+extern crate futures_io;
+#[cfg(feature = "tokio")]
+extern crate bytes;
+#[cfg(feature = "tokio")]
+extern crate tokio_util;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
 #[cfg(test)]
 extern crate test;
 
 #[cfg(test)]
 extern crate quickcheck;
+#[cfg(all(test, feature = "futures"))]
+extern crate futures_executor;
+
+/// Items of `core` and `alloc` used by the crate, taken from `std` when it is available.
+mod lib {
+    #[cfg(feature = "std")]
+    pub use std::{cell, cmp, default, f64, fmt, marker, mem, ops, result, slice, str};
+    #[cfg(not(feature = "std"))]
+    pub use core::{cell, cmp, default, f64, fmt, marker, mem, ops, result, slice, str};
+
+    #[cfg(feature = "std")]
+    pub use std::{borrow, boxed, collections, rc, string, sync, vec};
+    #[cfg(not(feature = "std"))]
+    pub use alloc::{borrow, boxed, collections, rc, string, sync, vec};
+
+    pub use self::borrow::{Cow, ToOwned};
+    pub use self::boxed::Box;
+    pub use self::string::{String, ToString};
+    pub use self::vec::Vec;
+}
+
+/// Paths used by the exported macros, not part of the public API.
+#[doc(hidden)]
+pub mod export {
+    pub use lib::ops;
+}
 
 #[macro_use]
 pub mod info;
+pub mod io;
 pub mod ser;
 pub mod de;
 pub mod der;
 pub mod universal;
+pub mod signed;
+
+use lib::Vec;
 
 pub use info::{ContentType, Tag, Len, Asn1Tagged, Asn1Typed, Asn1DisplayExt};
 pub use ser::{Asn1Serialize, Asn1Serializer, SeqSerializer};
-pub use de::{Asn1Deserialize, Asn1Deserializer, Asn1Visitor, SeqAccess};
-pub use universal::{ObjectIdentifier, OctetString, BitString};
+pub use de::{Asn1Deserialize, Asn1DeserializeOwned, Asn1Deserializer, Asn1Visitor, SeqAccess};
+pub use universal::{Any, ObjectIdentifier, OctetString, BitString, Value};
+pub use universal::{ObjectIdentifierRef, OctetStringRef, BitStringRef};
 
 pub fn to_asn1<T: Asn1Serialize>(value: &T) -> Result<Vec<u8>, der::EncodeError> {
     let mut buf: Vec<u8> = Vec::with_capacity(128);
@@ -42,9 +87,26 @@ pub fn to_asn1<T: Asn1Serialize>(value: &T) -> Result<Vec<u8>, der::EncodeError>
     Ok(buf)
 }
 
-pub fn from_asn1<T: Asn1Deserialize>(buf: &[u8]) -> Result<T, der::DecodeError> {
-    let deserializer = der::Deserializer::new(buf);
-    T::asn1_deserialize(deserializer)
+/// Encode `value` into `buf`, returns the length of encoding.
+pub fn to_slice<T: Asn1Serialize>(value: &T, buf: &mut [u8]) -> Result<usize, der::EncodeError> {
+    let mut sink = der::SliceSink::new(buf);
+    match value.asn1_serialize(der::Serializer::new(&mut sink)) {
+        Ok(()) => Ok(sink.position()),
+        Err(der::EncodeError::GeneralIO(ref e)) if e.kind() == io::ErrorKind::WriteZero => {
+            Err(der::EncodeError::BufferTooSmall { needed: encoded_len(value)? })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Compute the length of DER encoding of `value`.
+pub fn encoded_len<T: Asn1Serialize>(value: &T) -> Result<usize, der::EncodeError> {
+    value.asn1_serialize(der::LengthSerializer::new())
+}
+
+/// Decode `T` from DER encoded `buf`. Errors are located in the input, see `der::DecodeError`.
+pub fn from_asn1<'de, T: Asn1Deserialize<'de>>(buf: &'de [u8]) -> Result<T, der::DecodeError> {
+    der::Deserializer::new(buf).decode()
 }
 
 #[macro_export]
@@ -126,25 +188,25 @@ macro_rules! asn1_alias_ser {
 #[macro_export]
 macro_rules! asn1_alias_de {
     ($ty:ident ::= IMPLICIT $pty:ty) => (
-        impl $crate::Asn1Deserialize for $ty {
-            fn asn1_deserialize<'de, D: $crate::Asn1Deserializer<'de>>(deserializer: D)
-                                                                -> Result<Self, D::Err> {
+        impl<'de> $crate::Asn1Deserialize<'de> for $ty {
+            fn asn1_deserialize<D: $crate::Asn1Deserializer<'de>>(deserializer: D)
+                                                           -> Result<Self, D::Err> {
                 $crate::Asn1Deserialize::asn1_deserialize(deserializer.deserialize_tagged_implicit(Self::asn1_tag())?).map($ty)
             }
         }
     );
     ($ty:ident ::= EXPLICIT $pty:ty) => (
-        impl $crate::Asn1Deserialize for $ty {
-            fn asn1_deserialize<'de, D: $crate::Asn1Deserializer<'de>>(deserializer: D)
-                                                                -> Result<Self, D::Err> {
+        impl<'de> $crate::Asn1Deserialize<'de> for $ty {
+            fn asn1_deserialize<D: $crate::Asn1Deserializer<'de>>(deserializer: D)
+                                                           -> Result<Self, D::Err> {
                 $crate::Asn1Deserialize::asn1_deserialize(deserializer.deserialize_tagged(Self::asn1_tag())?).map($ty)
             }
         }
     );
     ($ty:ident ::= $pty:ty) => (
-        impl $crate::Asn1Deserialize for $ty {
-            fn asn1_deserialize<'de, D: $crate::Asn1Deserializer<'de>>(deserializer: D)
-                                                                -> Result<Self, D::Err> {
+        impl<'de> $crate::Asn1Deserialize<'de> for $ty {
+            fn asn1_deserialize<D: $crate::Asn1Deserializer<'de>>(deserializer: D)
+                                                           -> Result<Self, D::Err> {
                 $crate::Asn1Deserialize::asn1_deserialize(deserializer).map($ty)
             }
         }
@@ -188,9 +250,9 @@ mod tests {
         }
     }
 
-    impl de::Asn1Deserialize for TestStruct {
-        fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                               -> Result<Self, D::Err> {
+    impl<'de> de::Asn1Deserialize<'de> for TestStruct {
+        fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                          -> Result<Self, D::Err> {
             struct SeqVisitor;
             impl<'de> Asn1Visitor<'de> for SeqVisitor {
                 type Value = TestStruct;
@@ -223,9 +285,9 @@ mod tests {
         }
     }
 
-    impl de::Asn1Deserialize for TestStruct2 {
-        fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                               -> Result<Self, D::Err> {
+    impl<'de> de::Asn1Deserialize<'de> for TestStruct2 {
+        fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                          -> Result<Self, D::Err> {
             struct SeqVisitor;
             impl<'de> Asn1Visitor<'de> for SeqVisitor {
                 type Value = TestStruct2;
@@ -356,6 +418,23 @@ mod tests {
                })
     }
 
+    #[bench]
+    fn bench_nested_octet_string_ser(b: &mut test::Bencher) {
+        let mut buf = Vec::with_capacity(128 * 1024);
+
+        // every level has a long form length in front of the whole octet string
+        let test_obj = ::universal::OctetString::new(vec![0xaa; 64 * 1024]);
+        let test_nested = vec![vec![vec![vec![vec![vec![vec![vec![test_obj]]]]]]]];
+
+        b.iter(|| {
+                   {
+                       let writer = der::Serializer::new(&mut buf);
+                       test_nested.asn1_serialize(writer).unwrap();
+                   }
+                   buf.clear()
+               })
+    }
+
     #[bench]
     fn bench_seq_of_i64_ser(b: &mut test::Bencher) {
         let mut buf = Vec::with_capacity(512);
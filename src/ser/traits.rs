@@ -1,3 +1,7 @@
+use lib::Box;
+use lib::rc::Rc;
+use lib::sync::Arc;
+
 use info::Tag;
 
 pub trait Error: Sized {
@@ -42,9 +46,25 @@ pub trait Asn1Serializer {
 
     fn serialize_object_identifier(self, value: &[u64]) -> Result<Self::Ok, Self::Err>;
 
+    /// Serialize an already encoded TLV verbatim.
+    fn serialize_raw(self, value: &[u8]) -> Result<Self::Ok, Self::Err>;
+
     fn serialize_tagged(self, tag: Tag) -> Result<Self::ExplicitSerializer, Self::Err>;
     fn serialize_implicit(self, tag: Tag) -> Result<Self::ImplicitSerializer, Self::Err>;
     fn serialize_sequence(self) -> Result<Self::SeqSerializer, Self::Err>;
+
+    /// Serialize the items of `iter` as a SEQUENCE OF, without collecting them first.
+    fn serialize_iter<I>(self, iter: I) -> Result<Self::Ok, Self::Err>
+        where Self: Sized,
+              I: IntoIterator,
+              I::Item: Asn1Serialize
+    {
+        let mut seq_serializer = self.serialize_sequence()?;
+        for item in iter {
+            seq_serializer.serialize_field(&item)?;
+        }
+        seq_serializer.finish()
+    }
 }
 
 pub trait SeqSerializer {
@@ -57,3 +77,15 @@ pub trait SeqSerializer {
     fn finish(self) -> Result<Self::Ok, Self::Err>;
 }
 
+
+macro_rules! forward_serialize {
+    ($($ty:ty),*) => ($(
+        impl<'a, T: Asn1Serialize + ?Sized> Asn1Serialize for $ty {
+            fn asn1_serialize<S: Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                (**self).asn1_serialize(serializer)
+            }
+        }
+    )*)
+}
+
+forward_serialize!(&'a T, &'a mut T, Box<T>, Rc<T>, Arc<T>);
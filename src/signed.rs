@@ -0,0 +1,308 @@
+//! Signed structures of the form `SEQUENCE { tbs T, signatureAlgorithm AlgorithmIdentifier,
+//! signature BIT STRING }`, like certificates, CRLs and certification requests.
+//!
+//! Cryptography is left to the application, which implements `SignatureVerifier` and `Signer`.
+use lib::{fmt, String, Vec};
+use lib::marker::PhantomData;
+
+use info::TAG_SEQUENCE;
+use ser::{self, Asn1Serialize, SeqSerializer};
+use de::{self, Asn1DeserializeOwned, Asn1Visitor, SeqAccess};
+use der::EncodeError;
+use universal::{Any, BitString, Captured, ObjectIdentifier};
+
+/// Algorithm and its parameters, `parameters` being absent or of a type defined by `algorithm`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgorithmIdentifier {
+    pub algorithm: ObjectIdentifier,
+    pub parameters: Option<Any>,
+}
+
+impl AlgorithmIdentifier {
+    /// Create algorithm identifier without parameters.
+    pub fn new(algorithm: ObjectIdentifier) -> Self {
+        AlgorithmIdentifier {
+            algorithm: algorithm,
+            parameters: None,
+        }
+    }
+
+    pub fn with_parameters(algorithm: ObjectIdentifier, parameters: Any) -> Self {
+        AlgorithmIdentifier {
+            algorithm: algorithm,
+            parameters: Some(parameters),
+        }
+    }
+}
+
+asn1_info!(AlgorithmIdentifier => TAG_SEQUENCE, "AlgorithmIdentifier");
+
+impl ser::Asn1Serialize for AlgorithmIdentifier {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, s: S) -> Result<S::Ok, S::Err> {
+        let mut s = s.serialize_sequence()?;
+        s.serialize_field(&self.algorithm)?;
+        if let Some(ref parameters) = self.parameters {
+            s.serialize_field(parameters)?;
+        }
+        s.finish()
+    }
+}
+
+impl<'de> de::Asn1Deserialize<'de> for AlgorithmIdentifier {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D) -> Result<Self, D::Err> {
+        struct SeqVisitor;
+        impl<'de> Asn1Visitor<'de> for SeqVisitor {
+            type Value = AlgorithmIdentifier;
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Err>
+                where A: SeqAccess<'de>
+            {
+                let algorithm = seq.next_named_field("algorithm")?;
+                let parameters = if seq.remaining() > 0 {
+                    Some(seq.next_named_field("parameters")?)
+                } else {
+                    None
+                };
+                Ok(AlgorithmIdentifier {
+                       algorithm: algorithm,
+                       parameters: parameters,
+                   })
+            }
+        }
+        deserializer.deserialize_seq(SeqVisitor)
+    }
+}
+
+#[derive(Debug)]
+pub enum SignatureError {
+    /// Algorithm is not supported by the verifier or signer.
+    UnsupportedAlgorithm(ObjectIdentifier),
+    /// Signature does not match the signed data.
+    BadSignature,
+    /// Signed data can not be encoded.
+    Encode(EncodeError),
+    Custom(String),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SignatureError::UnsupportedAlgorithm(ref oid) => {
+                write!(f, "unsupported signature algorithm {}", oid)
+            }
+            SignatureError::BadSignature => f.write_str("bad signature"),
+            SignatureError::Encode(ref e) => write!(f, "can not encode signed data: {}", e),
+            SignatureError::Custom(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for SignatureError {
+    fn source(&self) -> Option<&(dyn (::std::error::Error) + 'static)> {
+        match *self {
+            SignatureError::Encode(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<EncodeError> for SignatureError {
+    fn from(e: EncodeError) -> Self {
+        SignatureError::Encode(e)
+    }
+}
+
+/// Verifier of signatures, supplied by the application.
+pub trait SignatureVerifier {
+    /// Check `signature` of `data` made with `algorithm`.
+    fn verify(&self,
+              algorithm: &AlgorithmIdentifier,
+              data: &[u8],
+              signature: &[u8])
+              -> Result<(), SignatureError>;
+}
+
+/// Signer of data, supplied by the application.
+pub trait Signer {
+    /// Sign `data` with `algorithm`.
+    fn sign(&self,
+            algorithm: &AlgorithmIdentifier,
+            data: &[u8])
+            -> Result<Vec<u8>, SignatureError>;
+}
+
+/// Value of type `T` with its signature. The encoding of `T` is kept as received, so the
+/// signature is verified over the exact signed bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signed<T> {
+    tbs: Captured<T>,
+    algorithm: AlgorithmIdentifier,
+    signature: BitString,
+}
+
+asn1_info!(Signed<T>: (T) => TAG_SEQUENCE, "SIGNED");
+
+impl<T: Asn1Serialize> Signed<T> {
+    /// Encode `tbs` and sign its encoding with `algorithm`.
+    pub fn sign(tbs: T,
+                algorithm: AlgorithmIdentifier,
+                signer: &dyn Signer)
+                -> Result<Self, SignatureError> {
+        let tbs = Captured::new(tbs)?;
+        let signature = signer.sign(&algorithm, tbs.as_bytes())?;
+        Ok(Signed {
+               tbs: tbs,
+               algorithm: algorithm,
+               signature: BitString::from_vec(signature, 0),
+           })
+    }
+}
+
+impl<T> Signed<T> {
+    /// Get the signed value.
+    pub fn tbs(&self) -> &T {
+        self.tbs.value()
+    }
+
+    /// Get the encoding of the signed value.
+    pub fn tbs_bytes(&self) -> &[u8] {
+        self.tbs.as_bytes()
+    }
+
+    pub fn algorithm(&self) -> &AlgorithmIdentifier {
+        &self.algorithm
+    }
+
+    pub fn signature(&self) -> &BitString {
+        &self.signature
+    }
+
+    /// Verify the signature of the signed value.
+    pub fn verify(&self, verifier: &dyn SignatureVerifier) -> Result<(), SignatureError> {
+        // signatures are whole octets
+        if self.signature.unused_bits() != 0 {
+            return Err(SignatureError::BadSignature);
+        }
+        verifier.verify(&self.algorithm, self.tbs.as_bytes(), self.signature.as_slice())
+    }
+
+    pub fn into_tbs(self) -> T {
+        self.tbs.into_inner()
+    }
+}
+
+impl<T> ser::Asn1Serialize for Signed<T> {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, s: S) -> Result<S::Ok, S::Err> {
+        let mut s = s.serialize_sequence()?;
+        s.serialize_field(&self.tbs)?;
+        s.serialize_field(&self.algorithm)?;
+        s.serialize_field(&self.signature)?;
+        s.finish()
+    }
+}
+
+impl<'de, T: Asn1DeserializeOwned> de::Asn1Deserialize<'de> for Signed<T> {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D) -> Result<Self, D::Err> {
+        struct SeqVisitor<T>(PhantomData<T>);
+        impl<'de, T: Asn1DeserializeOwned> Asn1Visitor<'de> for SeqVisitor<T> {
+            type Value = Signed<T>;
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Err>
+                where A: SeqAccess<'de>
+            {
+                Ok(Signed {
+                       tbs: seq.next_named_field("tbs")?,
+                       algorithm: seq.next_named_field("signatureAlgorithm")?,
+                       signature: seq.next_named_field("signature")?,
+                   })
+            }
+        }
+        deserializer.deserialize_seq(SeqVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use der::TlvBuilder;
+
+    fn alg() -> AlgorithmIdentifier {
+        AlgorithmIdentifier::new(ObjectIdentifier::new(vec![1, 3, 9999, 1]))
+    }
+
+    /// Toy signature: XOR of the data with the key.
+    struct XorKey(u8);
+
+    impl XorKey {
+        fn signature(&self, data: &[u8]) -> Vec<u8> {
+            vec![data.iter().fold(self.0, |acc, &b| acc ^ b)]
+        }
+    }
+
+    impl Signer for XorKey {
+        fn sign(&self,
+                algorithm: &AlgorithmIdentifier,
+                data: &[u8])
+                -> Result<Vec<u8>, SignatureError> {
+            if *algorithm != alg() {
+                return Err(SignatureError::UnsupportedAlgorithm(algorithm.algorithm.clone()));
+            }
+            Ok(self.signature(data))
+        }
+    }
+
+    impl SignatureVerifier for XorKey {
+        fn verify(&self,
+                  _algorithm: &AlgorithmIdentifier,
+                  data: &[u8],
+                  signature: &[u8])
+                  -> Result<(), SignatureError> {
+            if signature == self.signature(data).as_slice() {
+                Ok(())
+            } else {
+                Err(SignatureError::BadSignature)
+            }
+        }
+    }
+
+    #[test]
+    fn sign_verify() {
+        let signed = Signed::sign(vec![1u32, 2, 3], alg(), &XorKey(0x5a)).unwrap();
+        let buf = ::to_asn1(&signed).unwrap();
+
+        let decoded: Signed<Vec<u32>> = ::from_asn1(&buf).unwrap();
+        assert_eq!(decoded, signed);
+        assert_eq!(decoded.tbs(), &vec![1, 2, 3]);
+        decoded.verify(&XorKey(0x5a)).unwrap();
+        assert!(decoded.verify(&XorKey(0x5b)).is_err());
+
+        let other = AlgorithmIdentifier::with_parameters(ObjectIdentifier::new(vec![1, 2]),
+                                                         Any::from_value(&()).unwrap());
+        assert_eq!(::from_asn1::<AlgorithmIdentifier>(&::to_asn1(&other).unwrap()).unwrap(),
+                   other);
+        assert!(Signed::sign(1u32, other, &XorKey(0)).is_err());
+    }
+
+    #[test]
+    fn verify_received_bytes() {
+        // non-canonical TBS encoding is verified as received
+        let mut tbs = TlvBuilder::new();
+        tbs.sequence(|b| {
+                         b.padded_integer(1, 1);
+                     });
+        let tbs = tbs.into_vec();
+        let signature = XorKey(7).signature(&tbs);
+
+        let mut b = TlvBuilder::new();
+        b.sequence(|b| {
+                       b.raw(&tbs)
+                           .raw(&::to_asn1(&alg()).unwrap())
+                           .raw(&::to_asn1(&BitString::from_vec(signature.clone(), 0)).unwrap());
+                   });
+        let signed: Signed<Vec<i32>> = ::from_asn1(b.as_slice()).unwrap();
+        assert_eq!(signed.tbs_bytes(), tbs.as_slice());
+        signed.verify(&XorKey(7)).unwrap();
+        assert_eq!(::to_asn1(&signed).unwrap(), b.into_vec());
+    }
+}
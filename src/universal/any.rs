@@ -0,0 +1,191 @@
+// Module for ANY (open type)
+use lib::{fmt, ToString, Vec};
+
+use info::{self, Tag};
+use ser::{self, Asn1Serialize};
+use de::{self, Asn1Visitor, Asn1Error, Asn1Deserialize};
+use der;
+
+/// Value of any type, kept as its complete encoded TLV.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Any {
+    tag: Tag,
+    header_len: usize,
+    raw: Vec<u8>,
+}
+
+impl Any {
+    /// Encode `value` and keep its encoding.
+    pub fn from_value<T: Asn1Serialize>(value: &T) -> Result<Self, der::EncodeError> {
+        let raw = ::to_asn1(value)?;
+        // only the header is parsed, raw values like `Captured` are taken as encoded
+        let (tag, header_len) = {
+            let mut reader = der::TlvReader::new(&raw);
+            match reader.next_token() {
+                Ok(Some(der::Token::Start { tag, .. })) => (tag, reader.position()),
+                Ok(Some(der::Token::Primitive { tag, content })) => {
+                    (tag, raw.len() - content.len())
+                }
+                Ok(_) => return Err(der::EncodeError::InvalidValue),
+                Err(e) => return Err(der::EncodeError::Custom(e.to_string())),
+            }
+        };
+        Ok(Any {
+               tag: tag,
+               header_len: header_len,
+               raw: raw,
+           })
+    }
+
+    /// Get the tag of the value.
+    #[inline]
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Get the complete encoded TLV.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.raw.as_slice()
+    }
+
+    /// Get the content octets of the value.
+    #[inline]
+    pub fn content(&self) -> &[u8] {
+        &self.raw[self.header_len..]
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.raw
+    }
+
+    /// Decode the value as `T`.
+    pub fn decode<'a, T: Asn1Deserialize<'a>>(&'a self) -> Result<T, der::DecodeError> {
+        ::from_asn1(self.as_bytes())
+    }
+}
+
+impl fmt::Display for Any {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut use_prefix = false;
+        for x in self.raw.iter() {
+            let prefix = if use_prefix {
+                " "
+            } else {
+                ""
+            };
+            use_prefix = true;
+            write!(f, "{}{:02X}", prefix, x)?;
+        }
+        Ok(())
+    }
+}
+
+asn1_typed!(Any, info::TYPE_ANY);
+
+impl ser::Asn1Serialize for Any {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_raw(self.as_bytes())
+    }
+}
+
+impl<'de> de::Asn1Deserialize<'de> for Any {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        struct RawVisitor;
+        impl<'de> Asn1Visitor<'de> for RawVisitor {
+            type Value = Any;
+
+            fn visit_raw<E: Asn1Error>(self,
+                                       tag: Tag,
+                                       header_len: usize,
+                                       raw: Vec<u8>)
+                                       -> Result<Self::Value, E> {
+                Ok(Any {
+                       tag: tag,
+                       header_len: header_len,
+                       raw: raw,
+                   })
+            }
+        }
+        deserializer.deserialize_raw(RawVisitor)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Any;
+    use info;
+    use universal::{OctetString, ObjectIdentifier};
+    use universal::test_helper::ser_deser;
+
+    #[test]
+    fn any_integer() {
+        let v = Any::from_value(&-129i32).unwrap();
+        assert_eq!(v.tag(), info::TAG_INTEGER);
+        assert_eq!(v.as_bytes(), &[0x02, 0x02, 0xff, 0x7f]);
+        assert_eq!(v.content(), &[0xff, 0x7f]);
+        assert_eq!(v.decode::<i32>().unwrap(), -129);
+        assert_eq!(v, ser_deser(&v));
+    }
+
+    #[test]
+    fn any_from_value() {
+        let v = Any::from_value(&vec![1u32, 2]).unwrap();
+        assert_eq!(v.tag(), info::TAG_SEQUENCE);
+        assert_eq!(v.content(), &[0x02, 0x01, 0x01, 0x02, 0x01, 0x02]);
+        assert_eq!(v, ::from_asn1(v.as_bytes()).unwrap());
+
+        // long form length of a raw value is kept
+        let raw: Any = ::from_asn1(&[0x04, 0x81, 0x01, 0xaa]).unwrap();
+        let v = Any::from_value(&raw).unwrap();
+        assert_eq!(v, raw);
+        assert_eq!(v.content(), &[0xaa]);
+    }
+
+    #[test]
+    fn any_verbatim() {
+        // non-minimal length encoding must survive a round trip
+        let buf = [0x04, 0x81, 0x02, 0xca, 0xfe];
+        let v: Any = ::from_asn1(&buf).unwrap();
+        assert_eq!(v.content(), &[0xca, 0xfe]);
+        assert_eq!(::to_asn1(&v).unwrap().as_slice(), &buf);
+        assert_eq!(v.decode::<OctetString>().unwrap(),
+                   OctetString::from_slice(&[0xca, 0xfe]));
+    }
+
+    #[test]
+    fn any_in_sequence() {
+        let oid = ObjectIdentifier::new(vec![1, 2, 840, 113549]);
+        let seq = vec![Any::from_value(&oid).unwrap(), Any::from_value(&true).unwrap()];
+
+        let decoded: Vec<Any> = ser_deser(&seq);
+        assert_eq!(decoded, seq);
+        assert_eq!(decoded[0].decode::<ObjectIdentifier>().unwrap(), oid);
+        assert!(decoded[1].decode::<bool>().unwrap());
+        assert!(decoded[1].decode::<i32>().is_err());
+    }
+
+    #[test]
+    fn any_truncated() {
+        assert!(::from_asn1::<Any>(&[0x04, 0x03, 0x01]).is_err());
+    }
+
+    #[test]
+    fn any_implicit() {
+        use der;
+        use ser::{Asn1Serialize, Asn1Serializer};
+        use info::Tag;
+
+        let tag = Tag::primitive(info::ContextSpecific, 0);
+        let v = Any::from_value(&5u8).unwrap();
+
+        let mut buf = Vec::new();
+        {
+            let writer = der::Serializer::new(&mut buf);
+            v.asn1_serialize(writer.serialize_implicit(tag).unwrap()).unwrap();
+        }
+        assert_eq!(buf.as_slice(), &[0x80, 0x01, 0x05]);
+    }
+}
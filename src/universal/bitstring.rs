@@ -1,7 +1,7 @@
 // Module for BIT STRING
-use std::fmt;
+use lib::{fmt, Cow, Vec};
 
-use info;
+use info::{TAG_BIT_STRING, TYPE_BIT_STRING};
 use ser;
 use de::{self, Asn1Error, Asn1Visitor};
 
@@ -17,22 +17,177 @@ pub struct BitString {
 }
 
 impl BitString {
-    fn from_vec(data: Vec<u8>, unused: usize) -> Self {
-        assert!(unused < 8);
-        assert!(data.len() > 0 || unused == 0);
+    /// Create new empty BIT STRING.
+    pub fn new() -> Self {
         BitString {
-            unused: unused,
-            data: data,
+            unused: 0,
+            data: Vec::new(),
         }
     }
+
+    /// Create new BIT STRING of `len` zero bits.
+    pub fn with_len(len: usize) -> Self {
+        let mut out = BitString::new();
+        out.resize(len);
+        out
+    }
+
+    /// Create new BIT STRING from its encoded form: content bytes and number of unused bits
+    /// in the last byte.
+    ///
+    /// Panics if `unused` is more than 7, or not 0 for empty `data`, see `try_from_vec`.
+    pub fn from_vec(data: Vec<u8>, unused: usize) -> Self {
+        BitString::try_from_vec(data, unused).expect("invalid number of unused bits")
+    }
+
+    /// Create new BIT STRING from its encoded form like `from_vec`, `None` if the number of
+    /// unused bits is not valid.
+    pub fn try_from_vec(data: Vec<u8>, unused: usize) -> Option<Self> {
+        if unused > 7 || (data.is_empty() && unused != 0) {
+            return None;
+        }
+        Some(BitString {
+                 unused: unused,
+                 data: data,
+             })
+    }
+
+    /// Create new BIT STRING with one bit per element of `bits`.
+    pub fn from_bits(bits: &[bool]) -> Self {
+        let mut out = BitString::with_len(bits.len());
+        for (i, &bit) in bits.iter().enumerate() {
+            out.set(i, bit);
+        }
+        out
+    }
+
+    /// Number of bits.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len() * 8 - self.unused
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of unused bits in the last content byte.
+    #[inline]
+    pub fn unused_bits(&self) -> usize {
+        self.unused
+    }
+
+    /// Content bytes, first bit is the most significant bit of the first byte.
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    /// Get bit `i`. Bits past the end read as zero, as absent named bits do.
+    #[inline]
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len() && self.data[i / 8] & (0x80 >> (i % 8)) != 0
+    }
+
+    /// Set bit `i` to `value`, extending the BIT STRING if `i` is past the end.
+    pub fn set(&mut self, i: usize, value: bool) {
+        if i >= self.len() {
+            self.resize(i + 1);
+        }
+
+        let mask = 0x80 >> (i % 8);
+        if value {
+            self.data[i / 8] |= mask;
+        } else {
+            self.data[i / 8] &= !mask;
+        }
+    }
+
+    /// Truncate or extend the BIT STRING to `len` bits. New bits are zero.
+    pub fn resize(&mut self, len: usize) {
+        let bytes = (len + 7) / 8;
+        self.data.resize(bytes, 0);
+        self.unused = bytes * 8 - len;
+
+        // keep unused bits zero, as DER requires
+        if let Some(last) = self.data.last_mut() {
+            *last &= 0xff << self.unused;
+        }
+    }
+
+    /// Remove trailing zero bits, as DER requires for named bit lists (X.690 11.2.2).
+    pub fn trim_trailing_zeros(&mut self) {
+        let mut len = self.len();
+        while len > 0 && !self.get(len - 1) {
+            len -= 1;
+        }
+        self.resize(len);
+    }
+
+    /// Iterate over bits.
+    pub fn iter(&self) -> Iter {
+        Iter {
+            bits: self,
+            pos: 0,
+        }
+    }
+}
+
+impl Default for BitString {
+    fn default() -> Self {
+        BitString::new()
+    }
 }
 
-asn1_info!(BitString => info::TAG_BIT_STRING, info::TYPE_BIT_STRING);
+impl<'a> From<&'a [bool]> for BitString {
+    fn from(v: &[bool]) -> BitString {
+        BitString::from_bits(v)
+    }
+}
+
+impl<'a> IntoIterator for &'a BitString {
+    type Item = bool;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over bits of a BIT STRING.
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    bits: &'a BitString,
+    pos: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.pos < self.bits.len() {
+            self.pos += 1;
+            Some(self.bits.get(self.pos - 1))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.bits.len() - self.pos;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+asn1_info!(BitString => TAG_BIT_STRING, TYPE_BIT_STRING);
 
 impl fmt::Display for BitString {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for b in self.data.iter() {
-            write!(f, "{:b}", b)?;
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
         }
         Ok(())
     }
@@ -44,10 +199,10 @@ impl ser::Asn1Serialize for BitString {
     }
 }
 
-impl de::Asn1Deserialize for BitString {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
-        use std::fmt;
+impl<'de> de::Asn1Deserialize<'de> for BitString {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        use lib::fmt;
 
         struct BitStringVisitor;
 
@@ -59,9 +214,9 @@ impl de::Asn1Deserialize for BitString {
             }
 
             fn visit_bit_string<E: Asn1Error>(self, v: (u8, Vec<u8>)) -> Result<BitString, E> {
-                // todo: check unused value
                 let (unused, bytes) = v;
-                Ok(BitString::from_vec(bytes, unused as usize))
+                BitString::try_from_vec(bytes, unused as usize)
+                    .ok_or_else(|| E::invalid_value("unused bits of BIT STRING"))
             }
         }
 
@@ -69,12 +224,237 @@ impl de::Asn1Deserialize for BitString {
     }
 }
 
+/// BIT STRING borrowing its content from the decoded input when possible.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct BitStringRef<'a> {
+    unused: usize,
+    data: Cow<'a, [u8]>,
+}
+
+impl<'a> BitStringRef<'a> {
+    /// Create new BIT STRING from content bytes and number of unused bits in the last byte.
+    ///
+    /// Panics if `unused` is more than 7, or not 0 for empty `data`, see `try_new`.
+    pub fn new(data: &'a [u8], unused: usize) -> Self {
+        BitStringRef::try_new(data, unused).expect("invalid number of unused bits")
+    }
+
+    /// Create new BIT STRING like `new`, `None` if the number of unused bits is not valid.
+    pub fn try_new(data: &'a [u8], unused: usize) -> Option<Self> {
+        BitStringRef::from_cow(Cow::Borrowed(data), unused)
+    }
+
+    fn from_cow(data: Cow<'a, [u8]>, unused: usize) -> Option<Self> {
+        if unused > 7 || (data.is_empty() && unused != 0) {
+            return None;
+        }
+        Some(BitStringRef {
+                 unused: unused,
+                 data: data,
+             })
+    }
+
+    /// Get the number of bits.
+    pub fn len(&self) -> usize {
+        self.data.len() * 8 - self.unused
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of unused bits in the last byte.
+    pub fn unused_bits(&self) -> usize {
+        self.unused
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_owned(self) -> BitString {
+        BitString::from_vec(self.data.into_owned(), self.unused)
+    }
+}
+
+asn1_info!(BitStringRef<'a>: ('a) => TAG_BIT_STRING, TYPE_BIT_STRING);
+
+impl<'a> ser::Asn1Serialize for BitStringRef<'a> {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_bit_string((self.unused as u8, self.as_slice()))
+    }
+}
+
+impl<'de> de::Asn1Deserialize<'de> for BitStringRef<'de> {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        struct BitStringVisitor;
+
+        impl<'de> Asn1Visitor<'de> for BitStringVisitor {
+            type Value = BitStringRef<'de>;
+
+            fn visit_borrowed_bit_string<E: Asn1Error>(self,
+                                                       unused: u8,
+                                                       v: &'de [u8])
+                                                       -> Result<Self::Value, E> {
+                BitStringRef::try_new(v, unused as usize)
+                    .ok_or_else(|| E::invalid_value("unused bits of BIT STRING"))
+            }
+
+            fn visit_bit_string<E: Asn1Error>(self, v: (u8, Vec<u8>)) -> Result<Self::Value, E> {
+                let (unused, bytes) = v;
+                BitStringRef::from_cow(Cow::Owned(bytes), unused as usize)
+                    .ok_or_else(|| E::invalid_value("unused bits of BIT STRING"))
+            }
+        }
+
+        deserializer.deserialize_bit_string(BitStringVisitor)
+    }
+}
+
+/// Define a bitflags-style type for an ASN.1 named bit list.
+///
+/// ```ignore
+/// asn1_bits!(KeyUsage: "KeyUsage",
+///     DIGITAL_SIGNATURE = 0;
+///     NON_REPUDIATION = 1;
+///     KEY_ENCIPHERMENT = 2
+/// );
+/// ```
+///
+/// Bit numbers are ASN.1 named bit numbers (bit 0 comes first) and must be below 64.
+/// Encoding removes trailing zero bits, decoding ignores unknown bits.
+#[macro_export]
+macro_rules! asn1_bits {
+    ($ty:ident: $asn1_type:expr, $($flag:ident = $bit:expr);+) => (
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct $ty(u64);
+
+        #[allow(dead_code)]
+        impl $ty {
+            $(pub const $flag: $ty = $ty(1 << $bit);)+
+
+            pub fn empty() -> Self {
+                $ty(0)
+            }
+
+            pub fn all() -> Self {
+                $ty(0 $(| 1 << $bit)+)
+            }
+
+            pub fn bits(&self) -> u64 {
+                self.0
+            }
+
+            pub fn from_bits(bits: u64) -> Option<Self> {
+                if bits & !Self::all().0 == 0 {
+                    Some($ty(bits))
+                } else {
+                    None
+                }
+            }
+
+            pub fn from_bits_truncate(bits: u64) -> Self {
+                $ty(bits & Self::all().0)
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            pub fn contains(&self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0
+            }
+
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0
+            }
+
+            pub fn to_bit_string(&self) -> $crate::universal::BitString {
+                let mut out = $crate::universal::BitString::new();
+                for i in 0..64 {
+                    if self.0 & (1 << i) != 0 {
+                        out.set(i, true);
+                    }
+                }
+                out
+            }
+
+            pub fn from_bit_string(v: &$crate::universal::BitString) -> Self {
+                let mut bits = 0u64;
+                for (i, bit) in v.iter().take(64).enumerate() {
+                    if bit {
+                        bits |= 1 << i;
+                    }
+                }
+                Self::from_bits_truncate(bits)
+            }
+        }
+
+        impl $crate::export::ops::BitOr for $ty {
+            type Output = $ty;
+
+            fn bitor(self, other: $ty) -> $ty {
+                $ty(self.0 | other.0)
+            }
+        }
+
+        impl $crate::export::ops::BitAnd for $ty {
+            type Output = $ty;
+
+            fn bitand(self, other: $ty) -> $ty {
+                $ty(self.0 & other.0)
+            }
+        }
+
+        impl $crate::export::ops::Sub for $ty {
+            type Output = $ty;
+
+            fn sub(self, other: $ty) -> $ty {
+                $ty(self.0 & !other.0)
+            }
+        }
+
+        impl $crate::export::ops::BitOrAssign for $ty {
+            fn bitor_assign(&mut self, other: $ty) {
+                self.0 |= other.0
+            }
+        }
+
+        impl $crate::export::ops::BitAndAssign for $ty {
+            fn bitand_assign(&mut self, other: $ty) {
+                self.0 &= other.0
+            }
+        }
+
+        asn1_info!($ty => $crate::info::TAG_BIT_STRING, $asn1_type);
+
+        impl $crate::Asn1Serialize for $ty {
+            fn asn1_serialize<S: $crate::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                $crate::Asn1Serialize::asn1_serialize(&self.to_bit_string(), serializer)
+            }
+        }
+
+        impl<'de> $crate::Asn1Deserialize<'de> for $ty {
+            fn asn1_deserialize<D: $crate::Asn1Deserializer<'de>>(deserializer: D)
+                                                           -> Result<Self, D::Err> {
+                let v: $crate::universal::BitString = $crate::Asn1Deserialize::asn1_deserialize(deserializer)?;
+                Ok(Self::from_bit_string(&v))
+            }
+        }
+    );
+}
+
 
 #[cfg(test)]
 mod tests {
     use quickcheck::{Arbitrary, Gen};
 
-    use super::BitString;
+    use super::{BitString, BitStringRef};
     use universal::test_helper::ser_deser;
 
     impl Arbitrary for BitString {
@@ -90,9 +470,88 @@ mod tests {
         }
     }
 
+    asn1_bits!(KeyUsage: "KeyUsage",
+        DIGITAL_SIGNATURE = 0;
+        NON_REPUDIATION = 1;
+        KEY_ENCIPHERMENT = 2;
+        DATA_ENCIPHERMENT = 3;
+        KEY_AGREEMENT = 4;
+        KEY_CERT_SIGN = 5;
+        CRL_SIGN = 6;
+        ENCIPHER_ONLY = 7;
+        DECIPHER_ONLY = 8
+    );
+
     #[quickcheck]
     fn bit_string(v: BitString) -> bool {
         v == ser_deser(&v)
     }
-}
 
+    #[quickcheck]
+    fn bit_string_from_bits(bits: Vec<bool>) -> bool {
+        let v = BitString::from_bits(&bits);
+        v.len() == bits.len() && v.iter().collect::<Vec<bool>>() == bits
+    }
+
+    #[test]
+    fn bit_string_get_set() {
+        let mut v = BitString::with_len(3);
+        assert_eq!(v.as_slice(), &[0x00]);
+        assert_eq!(v.unused_bits(), 5);
+
+        v.set(1, true);
+        v.set(9, true);
+        assert_eq!(v.len(), 10);
+        assert_eq!(v.as_slice(), &[0x40, 0x40]);
+        assert!(v.get(1) && v.get(9) && !v.get(0) && !v.get(100));
+
+        v.set(9, false);
+        v.trim_trailing_zeros();
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.as_slice(), &[0x40]);
+        assert_eq!(v.unused_bits(), 6);
+    }
+
+    #[test]
+    fn bit_string_display() {
+        let v = BitString::from_vec(vec![0x05, 0x80], 7);
+        assert_eq!(v.to_string(), "000001011");
+        assert_eq!(BitString::new().to_string(), "");
+
+        assert_eq!(BitString::try_from_vec(vec![0x05, 0x80], 7), Some(v));
+        assert_eq!(BitString::try_from_vec(vec![0x05], 8), None);
+        assert_eq!(BitString::try_from_vec(Vec::new(), 1), None);
+        assert_eq!(BitStringRef::try_new(&[], 1), None);
+    }
+
+    #[test]
+    fn named_bits() {
+        use der;
+        use ser::Asn1Serialize;
+
+        let usage = KeyUsage::DIGITAL_SIGNATURE | KeyUsage::KEY_CERT_SIGN;
+        assert!(usage.contains(KeyUsage::KEY_CERT_SIGN));
+        assert!(!usage.contains(KeyUsage::CRL_SIGN));
+
+        let mut buf = Vec::new();
+        {
+            let writer = der::Serializer::new(&mut buf);
+            usage.asn1_serialize(writer).unwrap();
+        }
+        assert_eq!(buf.as_slice(), &[0x03, 0x02, 0x02, 0x84]);
+        assert_eq!(usage, ser_deser(&usage));
+
+        let decipher = KeyUsage::DECIPHER_ONLY;
+        assert_eq!(decipher.to_bit_string().as_slice(), &[0x00, 0x80]);
+        assert_eq!(KeyUsage::empty().to_bit_string(), BitString::new());
+    }
+
+    #[test]
+    fn bit_string_borrowed() {
+        let buf = [0x03, 0x03, 0x07, 0x05, 0x80];
+        let v: BitStringRef = ::from_asn1(&buf).unwrap();
+        assert_eq!(v.len(), 9);
+        assert_eq!(v.as_slice().as_ptr(), buf[3..].as_ptr());
+        assert_eq!(v.into_owned(), BitString::from_vec(vec![0x05, 0x80], 7));
+    }
+}
@@ -0,0 +1,140 @@
+// Module for values kept with their original encoding
+use lib::Vec;
+
+use info::Asn1Typed;
+use ser::{self, Asn1Serialize};
+use de::{self, Asn1DeserializeOwned};
+use der;
+
+/// Decoded value of type `T` together with its encoding as received, like the signed part of
+/// a certificate whose exact bytes are hashed.
+///
+/// The value is decoded in place, with the limits and the implicit tag of the deserializer, and
+/// serialized from the original encoding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Captured<T> {
+    value: T,
+    raw: Vec<u8>,
+}
+
+impl<T: Asn1Serialize> Captured<T> {
+    /// Encode `value` and keep its encoding.
+    pub fn new(value: T) -> Result<Self, der::EncodeError> {
+        let raw = ::to_asn1(&value)?;
+        Ok(Captured {
+               value: value,
+               raw: raw,
+           })
+    }
+}
+
+impl<T> Captured<T> {
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Get the complete encoded TLV of the value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Get the value and its encoding.
+    pub fn into_parts(self) -> (T, Vec<u8>) {
+        (self.value, self.raw)
+    }
+}
+
+impl<T: Asn1Typed> Asn1Typed for Captured<T> {
+    fn asn1_type() -> &'static str {
+        T::asn1_type()
+    }
+}
+
+impl<T> ser::Asn1Serialize for Captured<T> {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_raw(&self.raw)
+    }
+}
+
+impl<'de, T: Asn1DeserializeOwned> de::Asn1Deserialize<'de> for Captured<T> {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        let (value, raw) = deserializer.deserialize_captured()?;
+        Ok(Captured {
+               value: value,
+               raw: raw,
+           })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Captured;
+    use de::{Asn1Deserialize, DecoderConfig};
+    use der::{DecodeError, Deserializer, TlvBuilder};
+    use info::{ContextSpecific, Tag};
+
+    #[test]
+    fn captured() {
+        // long form lengths are kept as received
+        let mut b = TlvBuilder::new();
+        b.sequence(|b| {
+                       b.long_lengths(1).sequence(|b| {
+                                                      b.integer(1).integer(2);
+                                                  });
+                       b.long_lengths(0).sequence(|b| {
+                                                      b.integer(3);
+                                                  });
+                   });
+        let buf = b.into_vec();
+        let first = &buf[2..buf.len() - 5];
+
+        let v: Vec<Captured<Vec<u32>>> = ::from_asn1(&buf).unwrap();
+        assert_eq!(v[0].value(), &vec![1, 2]);
+        assert_eq!(v[0].as_bytes(), first);
+        assert_eq!(v[1].value(), &vec![3]);
+        assert_eq!(::to_asn1(&v).unwrap(), buf);
+
+        let mut v = Vec::<Captured<Vec<u32>>>::asn1_deserialize(Deserializer::from_reader(&buf[..]))
+            .unwrap();
+        assert_eq!(v.remove(0).into_parts(), (vec![1, 2], first.to_vec()));
+
+        let captured = Captured::new(vec![1u32, 2]).unwrap();
+        assert_eq!(captured.as_bytes(), ::to_asn1(&vec![1u32, 2]).unwrap().as_slice());
+    }
+
+    #[test]
+    fn captured_errors() {
+        // errors are located in the enclosing input
+        let buf = ::to_asn1(&vec![vec![1u32], vec![2, 300]]).unwrap();
+        let e = ::from_asn1::<Vec<Captured<Vec<u8>>>>(&buf).unwrap_err();
+        assert_eq!(e.path(), Some("SEQUENCE OF[1][1]"));
+        assert_eq!(e.offset(), Some(12));
+
+        let de = Deserializer::new(buf.as_slice()).with_config(DecoderConfig {
+            max_depth: 1,
+            ..DecoderConfig::default()
+        });
+        match *Vec::<Captured<Vec<u32>>>::asn1_deserialize(de).unwrap_err().kind() {
+            DecodeError::NestingTooDeep(1) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+    }
+    #[test]
+    fn captured_implicit() {
+        // [1] IMPLICIT SEQUENCE OF INTEGER
+        let tag = Tag::constructed(ContextSpecific, 1);
+        let buf = [0xa1, 0x06, 0x02, 0x01, 0x05, 0x02, 0x01, 0x06];
+        let captured = Captured::<Vec<u32>>::asn1_deserialize(Deserializer::new(&buf[..])
+                                                                   .with_tag(tag))
+            .unwrap();
+        assert_eq!(captured.into_parts(), (vec![5, 6], buf.to_vec()));
+
+        let de = Deserializer::new(&buf[..]).with_tag(Tag::constructed(ContextSpecific, 2));
+        assert!(Captured::<Vec<u32>>::asn1_deserialize(de).is_err());
+    }
+}
@@ -74,9 +74,9 @@ impl ser::Asn1Serialize for usize {
     }
 }
 
-impl de::Asn1Deserialize for i8 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for i8 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = i8;
@@ -89,9 +89,9 @@ impl de::Asn1Deserialize for i8 {
     }
 }
 
-impl de::Asn1Deserialize for i16 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for i16 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = i16;
@@ -104,9 +104,9 @@ impl de::Asn1Deserialize for i16 {
     }
 }
 
-impl de::Asn1Deserialize for i32 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for i32 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = i32;
@@ -119,9 +119,9 @@ impl de::Asn1Deserialize for i32 {
     }
 }
 
-impl de::Asn1Deserialize for i64 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for i64 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = i64;
@@ -134,9 +134,9 @@ impl de::Asn1Deserialize for i64 {
     }
 }
 
-impl de::Asn1Deserialize for isize {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for isize {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = isize;
@@ -149,9 +149,9 @@ impl de::Asn1Deserialize for isize {
     }
 }
 
-impl de::Asn1Deserialize for u8 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for u8 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = u8;
@@ -164,9 +164,9 @@ impl de::Asn1Deserialize for u8 {
     }
 }
 
-impl de::Asn1Deserialize for u16 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for u16 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = u16;
@@ -179,9 +179,9 @@ impl de::Asn1Deserialize for u16 {
     }
 }
 
-impl de::Asn1Deserialize for u32 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for u32 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = u32;
@@ -194,9 +194,9 @@ impl de::Asn1Deserialize for u32 {
     }
 }
 
-impl de::Asn1Deserialize for u64 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for u64 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = u64;
@@ -209,9 +209,9 @@ impl de::Asn1Deserialize for u64 {
     }
 }
 
-impl de::Asn1Deserialize for usize {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for usize {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = usize;
@@ -288,7 +288,7 @@ mod bench {
 
     use der;
     use ser::Asn1Serialize;
-    use de::Asn1Deserialize;
+    use de::Asn1DeserializeOwned;
 
     #[inline]
     fn ser_helper<T: Asn1Serialize>(b: &mut test::Bencher, v: T) {
@@ -316,14 +316,14 @@ mod bench {
 
     #[inline]
     fn de_helper<T>(b: &mut test::Bencher, v: T)
-        where T: PartialEq + Debug + Asn1Serialize + Asn1Deserialize
+        where T: PartialEq + Debug + Asn1Serialize + Asn1DeserializeOwned
     {
         let bytes = serialize(&v);
         let mut cur = io::Cursor::new(bytes.as_slice());
 
         b.iter(move || {
                    let new_v = {
-                       let reader = der::Deserializer::new(&mut cur);
+                       let reader = der::Deserializer::from_reader(&mut cur);
                        T::asn1_deserialize(reader).unwrap()
                    };
                    cur.set_position(0);
@@ -0,0 +1,215 @@
+// Module for values decoded on first access
+use lib::cell::OnceCell;
+
+use info::{Asn1Typed, Class};
+use ser::{self, Asn1Serialize};
+use de::{self, Asn1DeserializeOwned, DecoderConfig};
+use der;
+use universal::Any;
+
+/// Value of type `T` kept encoded until it is accessed.
+///
+/// Decoding checks only the TLV framing of the value. The value is decoded later with the
+/// limits of the deserializer it came from. It is re-serialized from the original encoding
+/// unless it was modified through `get_mut`. A value received with a tag that is not universal
+/// is decoded with that tag, as if it was implicitly tagged.
+#[derive(Debug, Clone)]
+pub struct Lazy<T> {
+    /// Original encoding, `None` once the value is modified.
+    raw: Option<Any>,
+    value: OnceCell<T>,
+    /// Limits of the deserializer the encoding came from.
+    config: DecoderConfig,
+    /// Offset and nesting depth of the encoding in the input it came from.
+    offset: usize,
+    depth: usize,
+}
+
+impl<T> Lazy<T> {
+    /// Wrap decoded `value`, it is serialized as usual.
+    pub fn new(value: T) -> Self {
+        Lazy {
+            raw: None,
+            value: OnceCell::from(value),
+            config: DecoderConfig::default(),
+            offset: 0,
+            depth: 0,
+        }
+    }
+
+    /// Get the original encoding, `None` if the value was created or modified.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.raw.as_ref().map(Any::as_bytes)
+    }
+
+    /// Check if the value was decoded already.
+    pub fn is_decoded(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+impl<T: Asn1DeserializeOwned> Lazy<T> {
+    /// Get the value, decoding it on first access. Errors are located in the input the
+    /// encoding came from.
+    pub fn get(&self) -> Result<&T, der::DecodeError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+
+        let value = match self.raw {
+            Some(ref raw) => {
+                let mut de = der::Deserializer::new(raw.as_bytes())
+                    .with_config(self.config)
+                    .with_depth(self.depth);
+                if raw.tag().class() != Class::Universal {
+                    de = de.with_tag(raw.tag());
+                }
+                de.decode().map_err(|e| e.shifted(self.offset))?
+            }
+            None => unreachable!("value without encoding"),
+        };
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Get the value for modification, it is serialized from the value afterwards.
+    pub fn get_mut(&mut self) -> Result<&mut T, der::DecodeError> {
+        self.get()?;
+        self.raw = None;
+        Ok(self.value.get_mut().expect("value is decoded"))
+    }
+
+    pub fn into_inner(self) -> Result<T, der::DecodeError> {
+        self.get()?;
+        Ok(self.value.into_inner().expect("value is decoded"))
+    }
+}
+
+impl<T: Asn1Typed> Asn1Typed for Lazy<T> {
+    fn asn1_type() -> &'static str {
+        T::asn1_type()
+    }
+}
+
+impl<T: Asn1Serialize> ser::Asn1Serialize for Lazy<T> {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        match (self.raw.as_ref(), self.value.get()) {
+            (Some(raw), _) => serializer.serialize_raw(raw.as_bytes()),
+            (None, Some(value)) => value.asn1_serialize(serializer),
+            (None, None) => unreachable!("value without encoding"),
+        }
+    }
+}
+
+impl<'de, T: Asn1Typed> de::Asn1Deserialize<'de> for Lazy<T> {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        let config = deserializer.decoder_config().unwrap_or_default();
+        let offset = deserializer.value_offset().unwrap_or(0);
+        let depth = deserializer.value_depth().unwrap_or(0);
+        let any = Any::asn1_deserialize(deserializer)?;
+        Ok(Lazy {
+               raw: Some(any),
+               value: OnceCell::new(),
+               config: config,
+               offset: offset,
+               depth: depth,
+           })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lazy;
+    use de::{Asn1Deserialize, DecoderConfig};
+    use der::{DecodeError, Deserializer, TlvBuilder};
+    use info::{ContextSpecific, Tag};
+    use universal::OctetString;
+
+    #[test]
+    fn lazy_untouched() {
+        let value = vec![OctetString::new(vec![1, 2, 3]), OctetString::new(vec![])];
+        let buf = ::to_asn1(&value).unwrap();
+
+        let lazy: Lazy<Vec<OctetString>> = ::from_asn1(&buf).unwrap();
+        assert!(!lazy.is_decoded());
+        assert_eq!(lazy.as_bytes(), Some(buf.as_slice()));
+        assert_eq!(lazy.get().unwrap(), &value);
+        assert!(lazy.is_decoded());
+        assert_eq!(::to_asn1(&lazy).unwrap(), buf);
+        assert_eq!(lazy.into_inner().unwrap(), value);
+    }
+
+    #[test]
+    fn lazy_modified() {
+        // non-canonical encoding is kept until the value is modified
+        let mut b = TlvBuilder::new();
+        b.padded_integer(5, 2);
+        let buf = b.into_vec();
+
+        let mut lazy: Lazy<i64> = ::from_asn1(&buf).unwrap();
+        assert_eq!(::to_asn1(&lazy).unwrap(), buf);
+        *lazy.get_mut().unwrap() += 1;
+        assert_eq!(lazy.as_bytes(), None);
+        assert_eq!(::to_asn1(&lazy).unwrap(), ::to_asn1(&6i64).unwrap());
+    }
+
+    #[test]
+    fn lazy_invalid() {
+        // framing is valid, the content is not
+        let mut b = TlvBuilder::new();
+        b.octet_string(&[]).raw(&[0x30, 0x03, 0x02, 0x01]);
+        assert!(::from_asn1::<Lazy<Vec<i32>>>(&b.as_slice()[2..]).is_err());
+
+        let lazy: Lazy<i32> = ::from_asn1(&b.as_slice()[..2]).unwrap();
+        assert!(lazy.get().is_err());
+        assert!(!lazy.is_decoded());
+
+        // errors are located in the input the encoding came from
+        let buf = ::to_asn1(&vec![vec![1u32], vec![2, 300]]).unwrap();
+        let v: Vec<Lazy<Vec<u8>>> = ::from_asn1(&buf).unwrap();
+        v[0].get().unwrap();
+        let e = v[1].get().unwrap_err();
+        assert_eq!(e.offset(), Some(12));
+        assert_eq!(e.path(), Some("SEQUENCE OF[1]"));
+    }
+
+    #[test]
+    fn lazy_implicit() {
+        // [1] IMPLICIT SEQUENCE OF INTEGER
+        let tag = Tag::constructed(ContextSpecific, 1);
+        let buf = [0xa1, 0x06, 0x02, 0x01, 0x05, 0x02, 0x01, 0x06];
+        let lazy = Lazy::<Vec<u32>>::asn1_deserialize(&mut Deserializer::new(&buf[..])
+                                                               .with_tag(tag))
+            .unwrap();
+        assert_eq!(lazy.get().unwrap(), &vec![5, 6]);
+        assert_eq!(::to_asn1(&lazy).unwrap(), &buf[..]);
+
+        // universal tags are not taken as implicit tags
+        let lazy: Lazy<Vec<u32>> = ::from_asn1(&[0x31, 0x03, 0x02, 0x01, 0x05]).unwrap();
+        match *lazy.get().unwrap_err().kind() {
+            DecodeError::TagMismatch(..) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+    }
+
+    #[test]
+    fn lazy_limits() {
+        let buf = ::to_asn1(&vec![vec![vec![1u8, 2, 3]]]).unwrap();
+        let decode = |config| {
+            let de = Deserializer::new(buf.as_slice()).with_config(config);
+            let v = Vec::<Lazy<Vec<Vec<u8>>>>::asn1_deserialize(de).unwrap();
+            v[0].get().map(|_| ())
+        };
+
+        let default = DecoderConfig::default();
+        decode(default).unwrap();
+        match *decode(DecoderConfig { max_depth: 2, ..default }).unwrap_err().kind() {
+            DecodeError::NestingTooDeep(2) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+        match *decode(DecoderConfig { max_seq_len: 2, ..default }).unwrap_err().kind() {
+            DecodeError::TooManyElements(2) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+    }
+}
@@ -1,5 +1,7 @@
+pub mod any;
 pub mod boolean;
 pub mod integer;
+pub mod lazy;
 pub mod object_identifier;
 pub mod octet_string;
 pub mod bitstring;
@@ -7,11 +9,19 @@ pub mod real;
 pub mod null;
 pub mod sequence;
 pub mod sequence_of;
+pub mod captured;
+pub mod value;
 
 #[cfg(test)]
 pub mod test_helper;
 
-pub use self::bitstring::BitString;
-pub use self::octet_string::OctetString;
-pub use self::object_identifier::ObjectIdentifier;
+pub use self::any::Any;
+pub use self::bitstring::{BitString, BitStringRef};
+pub use self::captured::Captured;
+pub use self::lazy::Lazy;
+pub use self::octet_string::{OctetString, OctetStringRef};
+pub use self::object_identifier::{ObjectIdentifier, ObjectIdentifierRef};
+pub use self::sequence::{Extensions, ExtensionGroup};
+pub use self::sequence_of::SeqOfIter;
+pub use self::value::Value;
 
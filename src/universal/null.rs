@@ -10,9 +10,9 @@ impl ser::Asn1Serialize for () {
     }
 }
 
-impl de::Asn1Deserialize for () {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for () {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct NullVisitor;
         impl<'de> Asn1Visitor<'de> for NullVisitor {
             type Value = ();
@@ -1,10 +1,11 @@
 // Module for OBJECT IDENTIFIER
-use std::{default, fmt, error};
-use std::str::FromStr;
+use lib::{default, fmt, Cow, Vec};
+use lib::str::FromStr;
 
-use info;
+use info::{TAG_OBJECT_IDENTIFIER, TYPE_OBJECT_IDENTIFIER};
 use ser;
 use de::{self, Asn1Visitor, Asn1Error};
+use der;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 #[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
@@ -55,7 +56,8 @@ impl fmt::Display for ParseObjectIdError {
     }
 }
 
-impl error::Error for ParseObjectIdError {
+#[cfg(feature = "std")]
+impl ::std::error::Error for ParseObjectIdError {
     fn description(&self) -> &str {
         "provided string is not a valid Object Identifier (dot separated natural numbers)"
     }
@@ -94,7 +96,7 @@ impl default::Default for ObjectIdentifier {
     }
 }
 
-asn1_info!(ObjectIdentifier => info::TAG_OBJECT_IDENTIFIER, info::TYPE_OBJECT_IDENTIFIER);
+asn1_info!(ObjectIdentifier => TAG_OBJECT_IDENTIFIER, TYPE_OBJECT_IDENTIFIER);
 
 impl ser::Asn1Serialize for ObjectIdentifier {
     fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
@@ -102,9 +104,9 @@ impl ser::Asn1Serialize for ObjectIdentifier {
     }
 }
 
-impl de::Asn1Deserialize for ObjectIdentifier {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for ObjectIdentifier {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct BytesVisitor;
         impl<'de> Asn1Visitor<'de> for BytesVisitor {
             type Value = ObjectIdentifier;
@@ -117,12 +119,154 @@ impl de::Asn1Deserialize for ObjectIdentifier {
     }
 }
 
+/// OBJECT IDENTIFIER kept as its content octets, borrowing them from the decoded input
+/// when possible.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct ObjectIdentifierRef<'a>(Cow<'a, [u8]>);
+
+impl<'a> ObjectIdentifierRef<'a> {
+    /// Wrap content octets of an OBJECT IDENTIFIER, `None` if they are not a valid encoding.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        if valid_content(bytes) {
+            Some(ObjectIdentifierRef(Cow::Borrowed(bytes)))
+        } else {
+            None
+        }
+    }
+
+    /// Get the content octets.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Iterate over the arcs.
+    pub fn arcs(&self) -> Arcs {
+        Arcs {
+            bytes: &self.0,
+            pos: 0,
+            second: None,
+        }
+    }
+
+    pub fn into_owned(self) -> ObjectIdentifier {
+        ObjectIdentifier::new(self.arcs().collect())
+    }
+}
+
+impl<'a> fmt::Display for ObjectIdentifierRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut has_fields = false;
+        for i in self.arcs() {
+            let prefix = if has_fields {
+                "."
+            } else {
+                ""
+            };
+            write!(f, "{}{}", prefix, i)?;
+            has_fields = true;
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the arcs of `ObjectIdentifierRef`.
+#[derive(Debug, Clone)]
+pub struct Arcs<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    second: Option<u64>,
+}
+
+impl<'a> Iterator for Arcs<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if let Some(arc) = self.second.take() {
+            return Some(arc);
+        }
+
+        let first = self.pos == 0;
+        let mut arc: u64 = 0;
+        while self.pos < self.bytes.len() {
+            let byte = self.bytes[self.pos];
+            self.pos += 1;
+            arc = (arc << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 != 0 {
+                continue;
+            }
+
+            if !first {
+                return Some(arc);
+            }
+            // the first subidentifier holds two arcs
+            let (arc0, arc1) = if arc < 80 {
+                (arc / 40, arc % 40)
+            } else {
+                (2, arc - 80)
+            };
+            self.second = Some(arc1);
+            return Some(arc0);
+        }
+        None
+    }
+}
+
+/// Check if `bytes` are base 128 subidentifiers without padding and of at most 64 bits.
+fn valid_content(bytes: &[u8]) -> bool {
+    let mut arc: u64 = 0;
+    let mut start = true;
+    for &byte in bytes {
+        if (start && byte == 0x80) || arc >> 57 != 0 {
+            return false;
+        }
+        arc = (arc << 7) | (byte & 0x7f) as u64;
+        start = byte & 0x80 == 0;
+        if start {
+            arc = 0;
+        }
+    }
+    !bytes.is_empty() && start
+}
+
+asn1_info!(ObjectIdentifierRef<'a>: ('a) => TAG_OBJECT_IDENTIFIER, TYPE_OBJECT_IDENTIFIER);
+
+impl<'a> ser::Asn1Serialize for ObjectIdentifierRef<'a> {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        let arcs: Vec<u64> = self.arcs().collect();
+        serializer.serialize_object_identifier(arcs.as_slice())
+    }
+}
+
+impl<'de> de::Asn1Deserialize<'de> for ObjectIdentifierRef<'de> {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        struct BytesVisitor;
+        impl<'de> Asn1Visitor<'de> for BytesVisitor {
+            type Value = ObjectIdentifierRef<'de>;
+
+            fn visit_borrowed_object_identifier<E: Asn1Error>(self,
+                                                              v: &'de [u8])
+                                                              -> Result<Self::Value, E> {
+                ObjectIdentifierRef::from_bytes(v)
+                    .ok_or_else(|| E::invalid_value("bad object identifier encoding"))
+            }
+
+            fn visit_object_identifier<E: Asn1Error>(self, v: Vec<u64>) -> Result<Self::Value, E> {
+                der::object_identifier_content(&v)
+                    .map(|content| ObjectIdentifierRef(Cow::Owned(content)))
+                    .map_err(|_| E::invalid_value("OBJECT IDENTIFIER arcs out of range"))
+            }
+        }
+        deserializer.deserialize_object_identifier(BytesVisitor)
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     use quickcheck::{Arbitrary, Gen};
 
-    use super::ObjectIdentifier;
+    use super::{ObjectIdentifier, ObjectIdentifierRef};
     use universal::test_helper::ser_deser;
 
     impl Arbitrary for ObjectIdentifier {
@@ -142,5 +286,68 @@ mod tests {
     fn object_identifier(v: ObjectIdentifier) -> bool {
         v == ser_deser(&v)
     }
+
+    #[quickcheck]
+    fn object_identifier_ref(v: ObjectIdentifier) -> bool {
+        let buf = ::to_asn1(&v).unwrap();
+        let oid: ObjectIdentifierRef = ::from_asn1(&buf).unwrap();
+        oid.as_bytes() == &buf[2..] && oid.into_owned() == v
+    }
+
+    #[test]
+    fn object_identifier_ref_from_reader() {
+        use std::io;
+        use der;
+        use de::Asn1Deserialize;
+
+        let buf = [0x06, 0x06, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d];
+        let reader = der::Deserializer::from_reader(io::Cursor::new(&buf[..]));
+        let oid = ObjectIdentifierRef::asn1_deserialize(reader).unwrap();
+        assert_eq!(oid.as_bytes(), &buf[2..]);
+        assert_eq!(oid.to_string(), "1.2.840.113549");
+        assert!(ObjectIdentifierRef::from_bytes(&[0x2a, 0x86]).is_none());
+    }
+
+    #[test]
+    fn object_identifier_first_arcs() {
+        // 2.999 and 2.100000 have first subidentifiers of two and three octets
+        for &(ref arcs, ref content) in [(vec![2u64, 999, 3], vec![0x88, 0x37, 0x03]),
+                                         (vec![2, 100000], vec![0x86, 0x8d, 0x70]),
+                                         (vec![1, 39], vec![0x4f])]
+            .iter() {
+            let oid = ObjectIdentifier::new(arcs.clone());
+            let buf = ::to_asn1(&oid).unwrap();
+            assert_eq!(&buf[2..], content.as_slice());
+            assert_eq!(::from_asn1::<ObjectIdentifier>(&buf).unwrap(), oid);
+
+            let oid_ref = ObjectIdentifierRef::from_bytes(content).unwrap();
+            assert_eq!(oid_ref.arcs().collect::<Vec<u64>>(), *arcs);
+            assert_eq!(oid_ref.into_owned(), oid);
+        }
+
+        for arcs in [vec![1u64], vec![1, 40], vec![3, 1]].iter() {
+            assert!(::to_asn1(&ObjectIdentifier::new(arcs.clone())).is_err());
+        }
+    }
+
+    #[test]
+    fn object_identifier_bad_content() {
+        // padded subidentifier
+        assert!(ObjectIdentifierRef::from_bytes(&[0x2a, 0x80, 0x01]).is_none());
+        assert!(ObjectIdentifierRef::from_bytes(&[0x80, 0x2a]).is_none());
+        // 70 bits
+        let long = [0x2a, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        assert!(ObjectIdentifierRef::from_bytes(&long).is_none());
+        assert!(ObjectIdentifierRef::from_bytes(&[]).is_none());
+        // 64 bits
+        let max = [0x2a, 0x81, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        let oid = ObjectIdentifierRef::from_bytes(&max).unwrap();
+        assert_eq!(oid.arcs().last(), Some(u64::max_value()));
+
+        let mut buf = vec![0x06, long.len() as u8];
+        buf.extend_from_slice(&long);
+        assert!(::from_asn1::<ObjectIdentifier>(&buf).is_err());
+        assert!(::from_asn1::<ObjectIdentifierRef>(&buf).is_err());
+    }
 }
 
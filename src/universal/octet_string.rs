@@ -1,7 +1,7 @@
 // Module for OCTET STRING
-use std::fmt;
+use lib::{fmt, Cow, Vec};
 
-use info;
+use info::{TAG_OCTET_STRING, TYPE_OCTET_STRING};
 use ser;
 use de::{self, Asn1Visitor, Asn1Error};
 
@@ -55,7 +55,7 @@ impl<'a> From<Vec<u8>> for OctetString {
 
 impl fmt::Display for OctetString {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use std::str::from_utf8;
+        use lib::str::from_utf8;
         match from_utf8(self.0.as_slice()) {
             Ok(s) => f.write_str(s),
             Err(_) => {
@@ -75,7 +75,7 @@ impl fmt::Display for OctetString {
     }
 }
 
-asn1_info!(OctetString => info::TAG_OCTET_STRING, info::TYPE_OCTET_STRING);
+asn1_info!(OctetString => TAG_OCTET_STRING, TYPE_OCTET_STRING);
 
 impl ser::Asn1Serialize for OctetString {
     fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
@@ -83,9 +83,9 @@ impl ser::Asn1Serialize for OctetString {
     }
 }
 
-impl de::Asn1Deserialize for OctetString {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for OctetString {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct BytesVisitor;
         impl<'de> Asn1Visitor<'de> for BytesVisitor {
             type Value = OctetString;
@@ -98,11 +98,56 @@ impl de::Asn1Deserialize for OctetString {
     }
 }
 
+/// OCTET STRING borrowing its content from the decoded input when possible.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct OctetStringRef<'a>(Cow<'a, [u8]>);
+
+impl<'a> OctetStringRef<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        OctetStringRef(Cow::Borrowed(data))
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_owned(self) -> OctetString {
+        OctetString::new(self.0.into_owned())
+    }
+}
+
+asn1_info!(OctetStringRef<'a>: ('a) => TAG_OCTET_STRING, TYPE_OCTET_STRING);
+
+impl<'a> ser::Asn1Serialize for OctetStringRef<'a> {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_bytes(self.as_slice())
+    }
+}
+
+impl<'de> de::Asn1Deserialize<'de> for OctetStringRef<'de> {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        struct BytesVisitor;
+        impl<'de> Asn1Visitor<'de> for BytesVisitor {
+            type Value = OctetStringRef<'de>;
+
+            fn visit_borrowed_bytes<E: Asn1Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+                Ok(OctetStringRef(Cow::Borrowed(v)))
+            }
+
+            fn visit_byte_string<E: Asn1Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(OctetStringRef(Cow::Owned(v)))
+            }
+        }
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
 
 
 #[cfg(test)]
 mod tests {
-    use super::OctetString;
+    use super::{OctetString, OctetStringRef};
     use quickcheck::{Arbitrary, Gen};
 
     use universal::test_helper::ser_deser;
@@ -123,5 +168,26 @@ mod tests {
     fn octet_string(v: OctetString) -> bool {
         v == ser_deser(&v)
     }
+
+    #[test]
+    fn octet_string_borrowed() {
+        let buf = [0x30, 0x06, 0x04, 0x01, 0xaa, 0x04, 0x01, 0xbb];
+        let v: Vec<OctetStringRef> = ::from_asn1(&buf).unwrap();
+        assert_eq!(v, vec![OctetStringRef::new(&[0xaa]), OctetStringRef::new(&[0xbb])]);
+        assert_eq!(v[1].as_slice().as_ptr(), buf[7..].as_ptr());
+        assert_eq!(v[0].clone().into_owned(), OctetString::from_slice(&[0xaa]));
+    }
+
+    #[test]
+    fn octet_string_from_reader() {
+        use std::io;
+        use der;
+        use de::Asn1Deserialize;
+
+        let buf = [0x04, 0x02, 0xca, 0xfe];
+        let reader = der::Deserializer::from_reader(io::Cursor::new(&buf[..]));
+        let v = OctetStringRef::asn1_deserialize(reader).unwrap();
+        assert_eq!(v.as_slice(), &[0xca, 0xfe]);
+    }
 }
 
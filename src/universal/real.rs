@@ -11,9 +11,9 @@ impl ser::Asn1Serialize for f32 {
     }
 }
 
-impl de::Asn1Deserialize for f32 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for f32 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = f32;
@@ -32,9 +32,9 @@ impl ser::Asn1Serialize for f64 {
     }
 }
 
-impl de::Asn1Deserialize for f64 {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<'de> de::Asn1Deserialize<'de> for f64 {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct IntegerVisitor;
         impl<'de> Asn1Visitor<'de> for IntegerVisitor {
             type Value = f64;
@@ -47,3 +47,26 @@ impl de::Asn1Deserialize for f64 {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use universal::test_helper::ser_deser;
+
+    #[quickcheck]
+    fn real_f64(v: f64) -> bool {
+        v == ser_deser(&v)
+    }
+
+    #[quickcheck]
+    fn real_f32(v: f32) -> bool {
+        v == ser_deser(&v)
+    }
+
+    #[test]
+    fn real_edge_cases() {
+        for &v in [0.0, -0.0, 3.75, -1e-300, ::std::f64::MAX, ::std::f64::INFINITY].iter() {
+            assert_eq!(v, ser_deser(&v));
+        }
+        assert!(ser_deser(&::std::f64::NAN).is_nan());
+    }
+}
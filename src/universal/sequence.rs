@@ -1,3 +1,9 @@
+use lib::{slice, Vec};
+
+use ser::SeqSerializer;
+use de::SeqAccess;
+use universal::Any;
+
 #[macro_export]
 macro_rules! asn1_seq {
     ($ty:ident: $asn1_type:expr, $($args:tt)+) => (
@@ -21,9 +27,17 @@ macro_rules! asn1_seq_ser {
         }
     );
     (__impl { $this:ident $seq:ident } $item:tt; $($args:tt)+) => (
-        $seq.serialize_field(&$this.$item)?;
+        asn1_seq_ser!(__impl { $this $seq } $item);
         asn1_seq_ser!(__impl { $this $seq } $($args)*)
     );
+    (__impl { $this:ident $seq:ident } [[ $group:tt ]]) => (
+        if let Some(ref group) = $this.$group {
+            $crate::universal::ExtensionGroup::serialize_fields(group, &mut $seq)?;
+        }
+    );
+    (__impl { $this:ident $seq:ident } ... $ext:tt) => (
+        $this.$ext.serialize_into(&mut $seq)?;
+    );
     (__impl { $this:ident $seq:ident } $item:tt) => (
         $seq.serialize_field(&$this.$item)?;
     );
@@ -32,9 +46,9 @@ macro_rules! asn1_seq_ser {
 #[macro_export]
 macro_rules! asn1_seq_de {
     ($ty:ident, $($args:tt)+) => (
-        impl $crate::Asn1Deserialize for $ty {
-            fn asn1_deserialize<'de, D: $crate::Asn1Deserializer<'de>>(deserializer: D)
-                                                                -> Result<Self, D::Err> {
+        impl<'de> $crate::Asn1Deserialize<'de> for $ty {
+            fn asn1_deserialize<D: $crate::Asn1Deserializer<'de>>(deserializer: D)
+                                                           -> Result<Self, D::Err> {
                 struct SeqVisitor;
                 impl<'de> $crate::de::Asn1Visitor<'de> for SeqVisitor {
                     type Value = $ty;
@@ -42,7 +56,7 @@ macro_rules! asn1_seq_de {
                     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Err>
                         where A: $crate::de::SeqAccess<'de>
                     {
-                        let v = asn1_seq_de!(__visit {$ty seq}, $($args)*);
+                        let v = asn1_seq_de!(__visit {$ty seq} [] $($args)*);
                         Ok(v)
                     }
                 }
@@ -50,17 +64,120 @@ macro_rules! asn1_seq_de {
             }
         }
     );
-    (__visit {$ty:ident $seq:ident}, $($args:tt);+) => (
-        $ty { $(
-            $args: $seq.next_field()?
-        ),* }
+    (__visit {$ty:ident $seq:ident} [$($done:tt)*] [[ $group:tt ]]; $($args:tt)+) => (
+        asn1_seq_de!(__visit {$ty $seq} [$($done)* $group: asn1_seq_de!(__group $seq),] $($args)+)
+    );
+    (__visit {$ty:ident $seq:ident} [$($done:tt)*] [[ $group:tt ]]) => (
+        asn1_seq_de!(__visit {$ty $seq} [$($done)* $group: asn1_seq_de!(__group $seq),])
+    );
+    (__visit {$ty:ident $seq:ident} [$($done:tt)*] ... $ext:tt) => (
+        asn1_seq_de!(__visit {$ty $seq} [$($done)*
+            $ext: $crate::universal::Extensions::deserialize_from(&mut $seq)?,])
+    );
+    (__visit {$ty:ident $seq:ident} [$($done:tt)*] $item:tt; $($args:tt)+) => (
+        asn1_seq_de!(__visit {$ty $seq} [$($done)*
+            $item: $seq.next_named_field(stringify!($item))?,] $($args)+)
+    );
+    (__visit {$ty:ident $seq:ident} [$($done:tt)*] $item:tt) => (
+        asn1_seq_de!(__visit {$ty $seq} [$($done)*
+            $item: $seq.next_named_field(stringify!($item))?,])
+    );
+    (__visit {$ty:ident $seq:ident} [$($done:tt)*]) => (
+        $ty { $($done)* }
+    );
+    (__group $seq:ident) => (
+        if $crate::de::SeqAccess::remaining(&$seq) > 0 {
+            Some($crate::universal::ExtensionGroup::deserialize_fields(&mut $seq)?)
+        } else {
+            None
+        }
+    );
+}
+
+/// Components of an extension addition group `[[ ... ]]`, encoded inline in the enclosing
+/// SEQUENCE. Groups are fields of type `Option<G>`, absent when the encoding ends before them.
+///
+/// Use `asn1_ext_group!(Group, a; b)` to implement it.
+pub trait ExtensionGroup: Sized {
+    fn serialize_fields<S: SeqSerializer>(&self, seq: &mut S) -> Result<(), S::Err>;
+    fn deserialize_fields<'de, A: SeqAccess<'de>>(seq: &mut A) -> Result<Self, A::Err>;
+}
+
+#[macro_export]
+macro_rules! asn1_ext_group {
+    ($ty:ident, $($args:tt)+) => (
+        impl $crate::universal::ExtensionGroup for $ty {
+            fn serialize_fields<S>(&self, seq: &mut S) -> Result<(), S::Err>
+                where S: $crate::ser::SeqSerializer
+            {
+                asn1_seq_ser!(__impl { self seq } $($args)+);
+                Ok(())
+            }
+
+            fn deserialize_fields<'de, A>(seq: &mut A) -> Result<Self, A::Err>
+                where A: $crate::de::SeqAccess<'de>
+            {
+                Ok(asn1_seq_de!(__visit {$ty seq} [] $($args)+))
+            }
+        }
     );
 }
 
+/// Unknown components following the extension marker `...` of a SEQUENCE, added by newer
+/// versions of its type. They are kept encoded and re-emitted verbatim.
+///
+/// The field of this type follows the marker in `asn1_seq!`, as in `a; b; ... ext`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extensions {
+    items: Vec<Any>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Extensions::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<Any> {
+        self.items.iter()
+    }
+
+    pub fn push(&mut self, value: Any) {
+        self.items.push(value)
+    }
+
+    /// Collect the remaining components of `seq`.
+    pub fn deserialize_from<'de, A: SeqAccess<'de>>(seq: &mut A) -> Result<Self, A::Err> {
+        let mut items = Vec::new();
+        while seq.remaining() > 0 {
+            items.push(seq.next_field()?);
+        }
+        Ok(Extensions { items: items })
+    }
+
+    /// Serialize the components into `seq`.
+    pub fn serialize_into<S: SeqSerializer>(&self, seq: &mut S) -> Result<(), S::Err> {
+        for item in &self.items {
+            seq.serialize_field(item)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use universal::test_helper::ser_deser;
+    use info;
+    use universal::OctetString;
+    use der::DecodeError;
 
     #[derive(Debug, PartialEq, PartialOrd)]
     struct Seq(i32, i32, i32);
@@ -72,9 +189,144 @@ mod tests {
         2
     );
 
+    #[derive(Debug, PartialEq)]
+    struct Inner {
+        id: i32,
+        data: OctetString,
+    }
+
+    asn1_seq!(
+        Inner: "INNER",
+        id;
+        data
+    );
+
+    #[derive(Debug, PartialEq)]
+    struct Outer {
+        version: i32,
+        items: Vec<Inner>,
+    }
+
+    asn1_seq!(
+        Outer: "OUTER",
+        version;
+        items
+    );
+
     #[test]
     fn seq() {
         let new_seq = Seq(0, 15, 65535);
         assert_eq!(new_seq, ser_deser(&new_seq));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn error_path() {
+        let inner = |id| {
+            Inner {
+                id: id,
+                data: OctetString::new(vec![0xaa]),
+            }
+        };
+        let outer = Outer {
+            version: 2,
+            items: vec![inner(1), inner(2)],
+        };
+        let mut buf = ::to_asn1(&outer).unwrap();
+        // tag of the last OCTET STRING
+        let offset = buf.len() - 3;
+        buf[offset] = 0x05;
+
+        let err = ::from_asn1::<Outer>(&buf).unwrap_err();
+        assert_eq!(err.offset(), Some(offset));
+        assert_eq!(err.path(), Some("OUTER.items[1].data"));
+        match *err.kind() {
+            DecodeError::TagMismatch(..) => {}
+            ref e => panic!("unexpected {:?}", e),
+        }
+        assert!(err.to_string().ends_with(&format!("in OUTER.items[1].data at offset {}", offset)));
+        match err.into_kind() {
+            DecodeError::TagMismatch(..) => {}
+            e => panic!("unexpected {:?}", e),
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Version1 {
+        a: i32,
+        ext: Extensions,
+    }
+
+    asn1_seq!(
+        Version1: "VERSION1",
+        a;
+        ... ext
+    );
+
+    #[derive(Debug, PartialEq)]
+    struct Group {
+        b: i32,
+        c: OctetString,
+    }
+
+    asn1_ext_group!(Group, b; c);
+
+    #[derive(Debug, PartialEq)]
+    struct Version2 {
+        a: i32,
+        group: Option<Group>,
+        ext: Extensions,
+    }
+
+    asn1_seq!(
+        Version2: "VERSION2",
+        a;
+        [[ group ]];
+        ... ext
+    );
+
+    #[test]
+    fn extensions() {
+        let v2 = Version2 {
+            a: 1,
+            group: Some(Group {
+                            b: 2,
+                            c: OctetString::new(vec![0xaa]),
+                        }),
+            ext: Extensions::new(),
+        };
+        let buf = ::to_asn1(&v2).unwrap();
+        assert_eq!(::from_asn1::<Version2>(&buf).unwrap(), v2);
+
+        // older version keeps the additions it does not know
+        let v1 = ::from_asn1::<Version1>(&buf).unwrap();
+        assert_eq!(v1.a, 1);
+        assert_eq!(v1.ext.len(), 2);
+        assert_eq!(::to_asn1(&v1).unwrap(), buf);
+
+        let v1 = Version1 {
+            a: 1,
+            ext: Extensions::new(),
+        };
+        let v2 = ::from_asn1::<Version2>(&::to_asn1(&v1).unwrap()).unwrap();
+        assert_eq!(v2.group, None);
+        assert!(v2.ext.is_empty());
+    }
+
+    #[test]
+    fn unknown_additions() {
+        let mut ext = Extensions::new();
+        for item in &[Any::from_value(&2i32).unwrap(),
+                      Any::from_value(&OctetString::new(vec![0xaa])).unwrap(),
+                      Any::from_value(&()).unwrap()] {
+            ext.push(item.clone());
+        }
+        let v1 = Version1 { a: 1, ext: ext };
+        let buf = ::to_asn1(&v1).unwrap();
+
+        let v2 = ::from_asn1::<Version2>(&buf).unwrap();
+        assert_eq!(v2.group.as_ref().map(|g| g.b), Some(2));
+        assert_eq!(v2.ext.iter().map(|v| v.tag()).collect::<Vec<_>>(),
+                   vec![info::TAG_NULL]);
+        assert_eq!(::to_asn1(&v2).unwrap(), buf);
+    }
+}
@@ -1,27 +1,79 @@
-use std::{fmt, marker};
+use lib::{fmt, marker, Vec};
+use lib::collections::VecDeque;
 
 use info::{self, TAG_SEQUENCE, TYPE_SEQUENCE_OF};
-use ser::{self, Asn1Serialize, SeqSerializer};
+use ser::{self, Asn1Serialize};
 use de::{self, Asn1Visitor, Asn1Deserialize as Asn1Deserialize, SeqAccess};
 
 
 asn1_info!(Vec<T>: (T) => TAG_SEQUENCE, TYPE_SEQUENCE_OF);
+asn1_info!([T]: (T) => TAG_SEQUENCE, TYPE_SEQUENCE_OF);
+asn1_info!([T; N]: (T, const N: usize) => TAG_SEQUENCE, TYPE_SEQUENCE_OF);
+asn1_info!(VecDeque<T>: (T) => TAG_SEQUENCE, TYPE_SEQUENCE_OF);
 
 impl<T: Asn1Serialize> Asn1Serialize for Vec<T> {
     fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
-        let mut seq_seriliazer = serializer.serialize_sequence()?;
-        for field in self.iter() {
-            seq_seriliazer.serialize_field(field)?
-        }
-        seq_seriliazer.finish()
+        serializer.serialize_iter(self)
+    }
+}
+
+impl<T: Asn1Serialize> Asn1Serialize for [T] {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_iter(self)
     }
 }
 
-impl<T: Asn1Deserialize> de::Asn1Deserialize for Vec<T> {
-    fn asn1_deserialize<'de, D: de::Asn1Deserializer<'de>>(deserializer: D)
-                                                           -> Result<Self, D::Err> {
+impl<T: Asn1Serialize, const N: usize> Asn1Serialize for [T; N] {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_iter(self)
+    }
+}
+
+impl<T: Asn1Serialize> Asn1Serialize for VecDeque<T> {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_iter(self)
+    }
+}
+
+/// SEQUENCE OF serialized from the items of an iterator, like rows streamed from a database.
+///
+/// Every serialization runs over a clone of the iterator, as the length of the encoding may be
+/// computed before it is written.
+pub struct SeqOfIter<I> {
+    iter: I,
+}
+
+impl<I> SeqOfIter<I>
+    where I: Iterator + Clone,
+          I::Item: Asn1Serialize
+{
+    pub fn new<T: IntoIterator<IntoIter = I>>(iter: T) -> Self {
+        SeqOfIter { iter: iter.into_iter() }
+    }
+}
+
+impl<I> fmt::Debug for SeqOfIter<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SeqOfIter")
+    }
+}
+
+asn1_info!(SeqOfIter<I>: (I) => TAG_SEQUENCE, TYPE_SEQUENCE_OF);
+
+impl<I> Asn1Serialize for SeqOfIter<I>
+    where I: Iterator + Clone,
+          I::Item: Asn1Serialize
+{
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.serialize_iter(self.iter.clone())
+    }
+}
+
+impl<'de, T: Asn1Deserialize<'de>> de::Asn1Deserialize<'de> for Vec<T> {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
         struct SeqOfVisitor<T>(marker::PhantomData<T>);
-        impl<'de, T: Asn1Deserialize> Asn1Visitor<'de> for SeqOfVisitor<T> {
+        impl<'de, T: Asn1Deserialize<'de>> Asn1Visitor<'de> for SeqOfVisitor<T> {
             type Value = Vec<T>;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -48,6 +100,9 @@ impl<T: Asn1Deserialize> de::Asn1Deserialize for Vec<T> {
 mod tests {
     use universal::{OctetString, BitString, ObjectIdentifier};
     use universal::test_helper::ser_deser;
+    use lib::collections::VecDeque;
+    use lib::rc::Rc;
+    use super::SeqOfIter;
 
     #[quickcheck]
     fn sequence_of_bool(v: Vec<bool>) -> bool {
@@ -108,4 +163,26 @@ mod tests {
     fn sequence_of_object_identifier(v: Vec<ObjectIdentifier>) -> bool {
         v == ser_deser(&v)
     }
+
+    #[test]
+    fn sequence_of_sources() {
+        let expected = ::to_asn1(&vec![1u32, 2, 3]).unwrap();
+        assert_eq!(::to_asn1(&[1u32, 2, 3]).unwrap(), expected);
+        assert_eq!(::to_asn1(&&[1u32, 2, 3][..]).unwrap(), expected);
+        assert_eq!(::to_asn1(&VecDeque::from(vec![1u32, 2, 3])).unwrap(), expected);
+        assert_eq!(::to_asn1(&Box::new([1u32, 2, 3])).unwrap(), expected);
+        assert_eq!(::to_asn1(&Rc::new(vec![1u32, 2, 3])).unwrap(), expected);
+        assert_eq!(::to_asn1(&vec![&1u32, &2, &3]).unwrap(), expected);
+
+        let seq = SeqOfIter::new((1u32..4).filter(|_| true));
+        assert_eq!(::to_asn1(&seq).unwrap(), expected);
+        assert_eq!(::to_asn1(&seq).unwrap(), expected);
+        let mut buf = [0; 4];
+        match ::to_slice(&seq, &mut buf) {
+            Err(::der::EncodeError::BufferTooSmall { needed: 11 }) => {}
+            r => panic!("unexpected {:?}", r),
+        }
+        assert_eq!(::to_asn1(&SeqOfIter::new(Vec::<u32>::new())).unwrap(),
+                   ::to_asn1(&Vec::<u32>::new()).unwrap());
+    }
 }
@@ -1,11 +1,11 @@
 use der;
 
 use ser::Asn1Serialize;
-use de::Asn1Deserialize;
+use de::Asn1DeserializeOwned;
 
 #[allow(dead_code)]
 pub fn ser_deser<T>(v: &T) -> T
-    where T: Asn1Serialize + for<'de> Asn1Deserialize
+    where T: Asn1Serialize + Asn1DeserializeOwned
 {
     let mut buf: Vec<u8> = Vec::new();
     {
@@ -0,0 +1,273 @@
+// Module for schema-less values
+use lib::{String, Vec};
+
+use info::{self, Tag};
+use ser::{self, Asn1Serializer, SeqSerializer};
+use de::{self, Asn1Visitor, Asn1Error, SeqAccess};
+use universal::{BitString, OctetString, ObjectIdentifier};
+
+/// Any ASN.1 value, decoded without knowing its schema.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Boolean(bool),
+    /// INTEGER that fits into `i64`, longer ones are decoded as `Unknown`.
+    Integer(i64),
+    BitString(BitString),
+    OctetString(OctetString),
+    Null,
+    ObjectIdentifier(ObjectIdentifier),
+    Real(f64),
+    Utf8String(String),
+    NumericString(String),
+    PrintableString(String),
+    Ia5String(String),
+    VisibleString(String),
+    UtcTime(String),
+    GeneralizedTime(String),
+    Sequence(Vec<Value>),
+    Set(Vec<Value>),
+    /// Constructed value with APPLICATION, CONTEXT SPECIFIC or PRIVATE tag.
+    Tagged { tag: Tag, contents: Vec<Value> },
+    /// Value of unsupported type: its tag and content octets.
+    Unknown { tag: Tag, bytes: Vec<u8> },
+}
+
+impl Value {
+    /// Get the tag the value is encoded with.
+    pub fn tag(&self) -> Tag {
+        match *self {
+            Value::Boolean(_) => info::TAG_BOOLEAN,
+            Value::Integer(_) => info::TAG_INTEGER,
+            Value::BitString(_) => info::TAG_BIT_STRING,
+            Value::OctetString(_) => info::TAG_OCTET_STRING,
+            Value::Null => info::TAG_NULL,
+            Value::ObjectIdentifier(_) => info::TAG_OBJECT_IDENTIFIER,
+            Value::Real(_) => info::TAG_REAL,
+            Value::Utf8String(_) => info::TAG_UTF8_STRING,
+            Value::NumericString(_) => info::TAG_NUMERIC_STRING,
+            Value::PrintableString(_) => info::TAG_PRINTABLE_STRING,
+            Value::Ia5String(_) => info::TAG_IA5_STRING,
+            Value::VisibleString(_) => info::TAG_VISIBLE_STRING,
+            Value::UtcTime(_) => info::TAG_UTC_TIME,
+            Value::GeneralizedTime(_) => info::TAG_GENERALIZED_TIME,
+            Value::Sequence(_) => info::TAG_SEQUENCE,
+            Value::Set(_) => info::TAG_SET,
+            Value::Tagged { tag, .. } |
+            Value::Unknown { tag, .. } => tag,
+        }
+    }
+}
+
+asn1_typed!(Value, info::TYPE_ANY);
+
+fn serialize_constructed<S>(serializer: S, contents: &[Value]) -> Result<S::Ok, S::Err>
+    where S: ser::Asn1Serializer
+{
+    let mut seq = serializer.serialize_sequence()?;
+    for v in contents.iter() {
+        seq.serialize_field(v)?;
+    }
+    seq.finish()
+}
+
+impl ser::Asn1Serialize for Value {
+    fn asn1_serialize<S: ser::Asn1Serializer>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        match *self {
+            Value::Boolean(v) => serializer.serialize_bool(v),
+            Value::Integer(v) => serializer.serialize_i64(v),
+            Value::BitString(ref v) => v.asn1_serialize(serializer),
+            Value::OctetString(ref v) => v.asn1_serialize(serializer),
+            Value::Null => serializer.serialize_null(),
+            Value::ObjectIdentifier(ref v) => v.asn1_serialize(serializer),
+            Value::Real(v) => serializer.serialize_f64(v),
+            Value::Utf8String(ref s) |
+            Value::NumericString(ref s) |
+            Value::PrintableString(ref s) |
+            Value::Ia5String(ref s) |
+            Value::VisibleString(ref s) |
+            Value::UtcTime(ref s) |
+            Value::GeneralizedTime(ref s) => {
+                serializer.serialize_implicit(self.tag())?.serialize_bytes(s.as_bytes())
+            }
+            Value::Sequence(ref contents) => serialize_constructed(serializer, contents),
+            Value::Set(ref contents) |
+            Value::Tagged { ref contents, .. } => {
+                serialize_constructed(serializer.serialize_implicit(self.tag())?, contents)
+            }
+            Value::Unknown { tag, ref bytes } => {
+                serializer.serialize_implicit(tag)?.serialize_bytes(bytes)
+            }
+        }
+    }
+}
+
+fn visit_contents<'de, A: SeqAccess<'de>>(mut seq: A) -> Result<Vec<Value>, A::Err> {
+    let mut out = Vec::new();
+    while seq.remaining() > 0 {
+        out.push(seq.next_field()?);
+    }
+    Ok(out)
+}
+
+impl<'de> de::Asn1Deserialize<'de> for Value {
+    fn asn1_deserialize<D: de::Asn1Deserializer<'de>>(deserializer: D)
+                                                      -> Result<Self, D::Err> {
+        struct ValueVisitor;
+        impl<'de> Asn1Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn visit_bool<E: Asn1Error>(self, v: bool) -> Result<Value, E> {
+                Ok(Value::Boolean(v))
+            }
+
+            fn visit_i64<E: Asn1Error>(self, v: i64) -> Result<Value, E> {
+                Ok(Value::Integer(v))
+            }
+
+            fn visit_f64<E: Asn1Error>(self, v: f64) -> Result<Value, E> {
+                Ok(Value::Real(v))
+            }
+
+            fn visit_null<E: Asn1Error>(self) -> Result<Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_object_identifier<E: Asn1Error>(self, v: Vec<u64>) -> Result<Value, E> {
+                Ok(Value::ObjectIdentifier(ObjectIdentifier::new(v)))
+            }
+
+            fn visit_bit_string<E: Asn1Error>(self, v: (u8, Vec<u8>)) -> Result<Value, E> {
+                let (unused, bytes) = v;
+                BitString::try_from_vec(bytes, unused as usize)
+                    .map(Value::BitString)
+                    .ok_or_else(|| E::invalid_value("unused bits of BIT STRING"))
+            }
+
+            fn visit_byte_string<E: Asn1Error>(self, v: Vec<u8>) -> Result<Value, E> {
+                Ok(Value::OctetString(OctetString::new(v)))
+            }
+
+            fn visit_string<E: Asn1Error>(self, tag: Tag, v: String) -> Result<Value, E> {
+                match tag {
+                    info::TAG_UTF8_STRING => Ok(Value::Utf8String(v)),
+                    info::TAG_NUMERIC_STRING => Ok(Value::NumericString(v)),
+                    info::TAG_PRINTABLE_STRING => Ok(Value::PrintableString(v)),
+                    info::TAG_IA5_STRING => Ok(Value::Ia5String(v)),
+                    info::TAG_VISIBLE_STRING => Ok(Value::VisibleString(v)),
+                    info::TAG_UTC_TIME => Ok(Value::UtcTime(v)),
+                    info::TAG_GENERALIZED_TIME => Ok(Value::GeneralizedTime(v)),
+                    _ => {
+                        Ok(Value::Unknown {
+                               tag: tag,
+                               bytes: v.into_bytes(),
+                           })
+                    }
+                }
+            }
+
+            fn visit_raw<E: Asn1Error>(self,
+                                       tag: Tag,
+                                       header_len: usize,
+                                       raw: Vec<u8>)
+                                       -> Result<Value, E> {
+                Ok(Value::Unknown {
+                       tag: tag,
+                       bytes: raw[header_len..].to_vec(),
+                   })
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Value, A::Err>
+                where A: SeqAccess<'de>
+            {
+                visit_contents(seq).map(Value::Sequence)
+            }
+
+            fn visit_set<A>(self, set: A) -> Result<Value, A::Err>
+                where A: SeqAccess<'de>
+            {
+                visit_contents(set).map(Value::Set)
+            }
+
+            fn visit_tagged<A>(self, tag: Tag, contents: A) -> Result<Value, A::Err>
+                where A: SeqAccess<'de>
+            {
+                visit_contents(contents).map(|contents| {
+                                                 Value::Tagged {
+                                                     tag: tag,
+                                                     contents: contents,
+                                                 }
+                                             })
+            }
+        }
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+    use info::{self, Tag};
+    use universal::{BitString, OctetString, ObjectIdentifier};
+    use universal::test_helper::ser_deser;
+
+    #[test]
+    fn value_round_trip() {
+        let v = Value::Sequence(vec![
+            Value::Boolean(true),
+            Value::Integer(-0x7fff_ffff_ffff),
+            Value::BitString(BitString::from_bits(&[true, false, true])),
+            Value::OctetString(OctetString::from_slice(b"\x00\x01")),
+            Value::Null,
+            Value::ObjectIdentifier(ObjectIdentifier::new(vec![2, 5, 4, 3])),
+            Value::Real(-1.5),
+            Value::Utf8String("Привет".to_owned()),
+            Value::PrintableString("Test CA".to_owned()),
+            Value::UtcTime("170101000000Z".to_owned()),
+            Value::Set(vec![Value::Integer(1), Value::Integer(2)]),
+            Value::Tagged {
+                tag: Tag::constructed(info::ContextSpecific, 3),
+                contents: vec![Value::Sequence(vec![]), Value::Null],
+            },
+            Value::Unknown {
+                tag: Tag::primitive(info::ContextSpecific, 2),
+                bytes: b"example.com".to_vec(),
+            },
+        ]);
+        assert_eq!(v, ser_deser(&v));
+    }
+
+    #[test]
+    fn value_unknown_schema() {
+        // TestStruct2 from the crate tests: [APPLICATION 31] of two [APPLICATION 30]
+        let buf = [0x7f, 0x1f, 0x0a, 0x7e, 0x03, 0x02, 0x01, 0x81, 0x7e, 0x03, 0x02, 0x01, 0x7f];
+        let inner = Value::Tagged {
+            tag: Tag::constructed(info::Application, 30),
+            contents: vec![Value::Integer(-127)],
+        };
+        let inner2 = Value::Tagged {
+            tag: Tag::constructed(info::Application, 30),
+            contents: vec![Value::Integer(127)],
+        };
+
+        let v: Value = ::from_asn1(&buf).unwrap();
+        assert_eq!(v,
+                   Value::Tagged {
+                       tag: Tag::constructed(info::Application, 31),
+                       contents: vec![inner, inner2],
+                   });
+        assert_eq!(::to_asn1(&v).unwrap().as_slice(), &buf[..]);
+    }
+
+    #[test]
+    fn value_big_integer() {
+        let buf = [0x02, 0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let v: Value = ::from_asn1(&buf).unwrap();
+        assert_eq!(v,
+                   Value::Unknown {
+                       tag: info::TAG_INTEGER,
+                       bytes: buf[2..].to_vec(),
+                   });
+        assert_eq!(::to_asn1(&v).unwrap().as_slice(), &buf[..]);
+    }
+}
@@ -0,0 +1,93 @@
+// Decoding hostile lengths, in its own binary as it installs a global allocator
+extern crate asn1_exp;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::Cursor;
+
+use asn1_exp::{Any, Asn1DeserializeOwned, BitString, ObjectIdentifier, OctetString, Value};
+use asn1_exp::der::Deserializer;
+
+/// Allocator recording the largest allocation made by every thread.
+struct TrackingAlloc;
+
+thread_local!(static LARGEST: Cell<usize> = Cell::new(0));
+
+fn record(size: usize) {
+    let _ = LARGEST.try_with(|largest| if size > largest.get() {
+                                 largest.set(size);
+                             });
+}
+
+unsafe impl GlobalAlloc for TrackingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: TrackingAlloc = TrackingAlloc;
+
+fn decode_all<T: Asn1DeserializeOwned>(input: &[u8]) {
+    let _ = asn1_exp::from_asn1::<T>(input);
+    let _ = T::asn1_deserialize(Deserializer::from_reader(Cursor::new(input)));
+}
+
+fn decode_any_type(input: &[u8]) {
+    decode_all::<Value>(input);
+    decode_all::<Any>(input);
+    decode_all::<OctetString>(input);
+    decode_all::<BitString>(input);
+    decode_all::<ObjectIdentifier>(input);
+    decode_all::<Vec<Vec<u8>>>(input);
+    decode_all::<Vec<ObjectIdentifier>>(input);
+    decode_all::<f64>(input);
+}
+
+#[test]
+fn hostile_lengths_do_not_allocate() {
+    let inputs: [&[u8]; 6] = [&[0x04, 0x88, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xf0],
+                              &[0x30, 0x84, 0x7f, 0xff, 0xff, 0xff, 0x04, 0x84, 0x7f, 0xff],
+                              &[0x1f, 0x81, 0x00, 0x86, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00],
+                              &[0x02, 0x87, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+                              &[0x30, 0x88, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00],
+                              &[0x09, 0x85, 0x10, 0x00, 0x00, 0x00, 0x00, 0x03, 0x31]];
+
+    LARGEST.with(|largest| largest.set(0));
+    for input in inputs.iter() {
+        decode_any_type(input);
+    }
+
+    // xorshift, biased to constructed and variable length tags and long form lengths
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..20000 {
+        let mut input = [0u8; 10];
+        for byte in input.iter_mut() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *byte = state as u8;
+        }
+        input[0] = [0x30, 0x04, 0x06, 0x02, 0x09, 0x31, input[0]][(state % 7) as usize];
+        input[1] |= 0x80;
+        decode_any_type(&input);
+    }
+
+    let largest = LARGEST.with(|largest| largest.get());
+    assert!(largest < 64 * 1024, "allocated {} bytes", largest);
+}